@@ -0,0 +1,268 @@
+//! `archi bench`: the same scene along the same camera path every time,
+//! rendered offscreen with a fixed time step, with frame times and the time
+//! of each profiled pass printed as JSON.
+
+use crate::camera::path::CameraPath;
+use crate::context::{self, OffscreenBuffer, Output};
+use crate::{profiler, skybox::Skybox, AppState};
+use anyhow::Context as _;
+use luminance_front::context::GraphicsContext;
+use std::time::Instant;
+
+const USAGE: &str = "usage: archi bench [--frames N] [--size WIDTHxHEIGHT] \
+                     [--path CAMERA_PATH]";
+
+/// Frames rendered before measuring, for the driver to settle.
+const WARM_UP_FRAMES: usize = 30;
+
+/// Time step of the simulation and the camera path.
+const DELTA_T: f32 = 1.0 / 60.0;
+
+/// Seed of the waves, fixed for every run to look and perform the same.
+const OCEAN_SEED: u64 = 0x0cea4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub frames: usize,
+    pub size: [u32; 2],
+    /// A camera path recorded with `ARCHI_RECORD_CAMERA`, or the built in
+    /// one.
+    pub path: Option<std::path::PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            frames: 600,
+            size: [1920, 1080],
+            path: None,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(
+        mut args: impl Iterator<Item = String>,
+    ) -> anyhow::Result<Self> {
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next().with_context(|| {
+                    format!("{} needs a value\n{}", arg, USAGE)
+                })
+            };
+            match arg.as_str() {
+                "--frames" => {
+                    options.frames = value()?.parse().context(USAGE)?;
+                    anyhow::ensure!(options.frames > 0, "no frames to render");
+                }
+                "--size" => {
+                    let value = value()?;
+                    let sides = value
+                        .split('x')
+                        .map(str::parse)
+                        .collect::<Result<Vec<u32>, _>>()
+                        .context(USAGE)?;
+                    options.size = match sides[..] {
+                        [width, height] if width > 0 && height > 0 => {
+                            [width, height]
+                        }
+                        _ => anyhow::bail!("Bad size {:?}\n{}", value, USAGE),
+                    };
+                }
+                "--path" => options.path = Some(value()?.into()),
+                _ => anyhow::bail!("Unknown option {:?}\n{}", arg, USAGE),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Frame times in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Summary {
+    mean: f32,
+    p95: f32,
+    p99: f32,
+    max: f32,
+}
+
+impl Summary {
+    fn of(times: &[f32]) -> Self {
+        let mut sorted = times.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        // Nearest rank
+        let percentile = |p: f32| {
+            let rank = (p / 100.0 * sorted.len() as f32).ceil() as usize;
+            sorted[rank.max(1).min(sorted.len()) - 1]
+        };
+
+        Self {
+            mean: times.iter().sum::<f32>() / times.len() as f32,
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => {
+                json.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+pub fn run(options: Options) -> anyhow::Result<()> {
+    let path = match &options.path {
+        Some(file) => {
+            let text = std::fs::read_to_string(file).with_context(|| {
+                format!("failed to read {}", file.display())
+            })?;
+            CameraPath::parse(&text).with_context(|| {
+                format!("failed to parse {}", file.display())
+            })?
+        }
+        None => CameraPath::builtin(),
+    };
+
+    // The window is never shown, it only holds the GL context
+    let event_loop = glutin::event_loop::EventLoop::new();
+    let (mut context, _surface) = context::Surface::new(&event_loop);
+    let gl_messages = crate::debug::glerr::DebugMessages::from_env()?;
+
+    let size = options.size;
+    let skybox = Skybox::builtin(&mut context)?;
+    let mut state =
+        AppState::new(&mut context, size, skybox, OCEAN_SEED, gl_messages)?;
+    let output: OffscreenBuffer =
+        context.new_framebuffer(size, 0, Default::default())?;
+
+    profiler::set_enabled(true)?;
+    let mut frame_ms = Vec::with_capacity(options.frames);
+    for frame in 0..WARM_UP_FRAMES + options.frames {
+        if frame == WARM_UP_FRAMES {
+            // Start over, without the warm up frames
+            profiler::set_enabled(false)?;
+            profiler::set_enabled(true)?;
+        }
+
+        let t = frame.saturating_sub(WARM_UP_FRAMES) as f32 * DELTA_T;
+        state.camera.follow(&path.sample(t));
+
+        // Each frame is waited for, to time it from start to finish
+        let start = Instant::now();
+        crate::draw(t, &mut context, &mut state, Output::Offscreen(&output))?;
+        unsafe { gl::Finish() };
+        let elapsed = start.elapsed();
+
+        profiler::end_frame()?;
+        state.gl_messages.check();
+        if frame >= WARM_UP_FRAMES {
+            frame_ms.push(elapsed.as_secs_f32() * 1e3);
+        }
+    }
+
+    let Summary {
+        mean,
+        p95,
+        p99,
+        max,
+    } = Summary::of(&frame_ms);
+    let passes = profiler::overall_averages()
+        .iter()
+        .map(|average| {
+            format!(
+                "    {}: {{ \"cpu_ms\": {}, \"gpu_ms\": {} }}",
+                json_string(average.name),
+                average.cpu_ms,
+                average.gpu_ms
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+
+    println!("{{");
+    println!(
+        "  \"renderer\": {},",
+        json_string(&context.capabilities.renderer)
+    );
+    println!(
+        "  \"fft_backend\": {},",
+        json_string(&format!("{:?}", state.ocean.fft.backend()))
+    );
+    println!(
+        "  \"fft_precision\": {},",
+        json_string(&format!("{:?}", state.ocean.fft.precision()))
+    );
+    println!("  \"size\": [{}, {}],", size[0], size[1]);
+    println!("  \"frames\": {},", options.frames);
+    println!("  \"delta_t\": {},", DELTA_T);
+    println!(
+        "  \"frame_ms\": {{ \"mean\": {}, \"p95\": {}, \"p99\": {}, \"max\": {} }},",
+        mean, p95, p99, max
+    );
+    println!("  \"passes\": {{\n{}\n  }}", passes);
+    println!("}}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Options> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(parse(&[]).unwrap(), Options::default());
+        assert_eq!(
+            parse(&["--size", "640x480", "--frames", "10", "--path", "p"])
+                .unwrap(),
+            Options {
+                frames: 10,
+                size: [640, 480],
+                path: Some("p".into()),
+            }
+        );
+        assert!(parse(&["--size", "640"]).is_err());
+        assert!(parse(&["--frames"]).is_err());
+        assert!(parse(&["--frames", "0"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+    }
+
+    #[test]
+    fn percentiles() {
+        let times: Vec<f32> = (1..=100).rev().map(|t| t as f32).collect();
+        assert_eq!(
+            Summary::of(&times),
+            Summary {
+                mean: 50.5,
+                p95: 95.0,
+                p99: 99.0,
+                max: 100.0,
+            }
+        );
+        assert_eq!(Summary::of(&[3.0]).p99, 3.0);
+    }
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(json_string(r#"a "b" \ c"#), r#""a \"b\" \\ c""#);
+        assert_eq!(json_string("\n"), r#""\u000a""#);
+    }
+}
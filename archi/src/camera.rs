@@ -1,3 +1,5 @@
+pub mod path;
+
 #[derive(Debug)]
 pub struct Camera {
     position: glm::Vec3,
@@ -83,4 +85,153 @@ impl Camera {
     pub fn view(&self) -> glm::Mat4 {
         glm::translate(&glm::transpose(&self.orientation), &-self.position)
     }
+
+    /// The current pose, for recording a camera path.
+    pub fn key(&self, time: f32) -> path::Key {
+        path::Key {
+            time,
+            position: self.position,
+            pitch: self.pitch,
+            yaw: self.yaw,
+        }
+    }
+
+    /// Jump to the pose of `key`, at rest.
+    pub fn follow(&mut self, key: &path::Key) {
+        self.position = key.position;
+        self.velocity = glm::zero();
+        self.acceleration = glm::zero();
+        self.pitch = key.pitch;
+        self.yaw = key.yaw;
+        self.orientation =
+            glm::Mat4::from_euler_angles(self.pitch, self.yaw, 0.0);
+    }
+
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&(self.projection * self.view()))
+    }
+}
+
+/// The planes bounding what a view-projection matrix puts on screen, facing
+/// inwards, as (normal, distance) with unit normals.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    planes: [glm::Vec4; 6],
+}
+
+impl Frustum {
+    /// Extract the planes from the rows of the matrix (Gribb & Hartmann).
+    pub fn from_matrix(view_projection: &glm::Mat4) -> Self {
+        let row = |i| glm::Vec4::from(view_projection.row(i).transpose());
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let mut planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+        for plane in planes.iter_mut() {
+            // An infinite projection has no far plane; what is left of it
+            // has no normal and lets everything through.
+            let length = plane.xyz().norm();
+            if length > f32::EPSILON {
+                *plane /= length;
+            }
+        }
+
+        Self { planes }
+    }
+
+    fn distance(plane: &glm::Vec4, point: &glm::Vec3) -> f32 {
+        plane.xyz().dot(point) + plane.w
+    }
+
+    #[cfg(test)]
+    pub fn contains_point(&self, point: &glm::Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| Self::distance(plane, point) >= 0.0)
+    }
+
+    /// Whether any part of the axis aligned box may be visible. Boxes near
+    /// the corners of the frustum can pass without being inside it.
+    pub fn intersects_aabb(&self, min: &glm::Vec3, max: &glm::Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the normal
+            let corner = glm::vec3(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            Self::distance(plane, &corner) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Square viewport at the origin, looking down -z
+    fn camera() -> Camera {
+        Camera::new(100, 100)
+    }
+
+    #[test]
+    fn points_in_front_and_behind() {
+        let mut camera = camera();
+        camera.position = glm::zero();
+        let frustum = camera.frustum();
+
+        assert!(frustum.contains_point(&glm::vec3(0.0, 0.0, -1.0)));
+        assert!(frustum.contains_point(&glm::vec3(0.0, 0.0, -1.0e6)));
+        assert!(!frustum.contains_point(&glm::vec3(0.0, 0.0, 1.0)));
+        // Closer than the near plane
+        assert!(!frustum.contains_point(&glm::vec3(0.0, 0.0, -0.01)));
+    }
+
+    #[test]
+    fn points_at_the_sides() {
+        let mut camera = camera();
+        camera.position = glm::zero();
+        let frustum = camera.frustum();
+
+        // The field of view is 1.1 radians, so at a distance of 10 the
+        // edges of the screen are about 6.2 away from the center
+        for &(x, y) in &[(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)] {
+            let inside = glm::vec3(5.5 * x, 5.5 * y, -10.0);
+            let outside = glm::vec3(7.0 * x, 7.0 * y, -10.0);
+            assert!(frustum.contains_point(&inside), "{:?}", inside);
+            assert!(!frustum.contains_point(&outside), "{:?}", outside);
+        }
+    }
+
+    #[test]
+    fn boxes() {
+        let mut camera = camera();
+        camera.position = glm::vec3(10.0, 5.0, 0.0);
+        let frustum = camera.frustum();
+
+        let visible = |min: [f32; 3], max: [f32; 3]| {
+            frustum.intersects_aabb(&min.into(), &max.into())
+        };
+
+        // Around the view direction
+        assert!(visible([9.0, 4.0, -20.0], [11.0, 6.0, -10.0]));
+        // Behind the camera
+        assert!(!visible([9.0, 4.0, 1.0], [11.0, 6.0, 10.0]));
+        // Off to the left, and straddling the left edge
+        assert!(!visible([-20.0, 4.0, -11.0], [-10.0, 6.0, -9.0]));
+        assert!(visible([-20.0, 4.0, -11.0], [10.0, 6.0, -9.0]));
+        // Containing the camera
+        assert!(visible([0.0, 0.0, -1.0], [20.0, 10.0, 1.0]));
+    }
+
+    #[test]
+    fn follows_orientation() {
+        let mut camera = camera();
+        camera.position = glm::zero();
+        // Turn left, to look down -x
+        camera.mouse_moved(-std::f64::consts::FRAC_PI_2 * 128.0, 0.0);
+        let frustum = camera.frustum();
+
+        assert!(frustum.contains_point(&glm::vec3(-10.0, 0.0, 0.0)));
+        assert!(!frustum.contains_point(&glm::vec3(0.0, 0.0, -10.0)));
+    }
 }
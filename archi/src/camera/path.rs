@@ -0,0 +1,178 @@
+//! Camera poses over time, recorded from a session or built in, for flying
+//! the camera the same way every time.
+//!
+//! Stored as text, a key per line: time in seconds, position, pitch and yaw,
+//! separated by spaces.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Key {
+    pub time: f32,
+    pub position: glm::Vec3,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            time,
+            position,
+            pitch,
+            yaw,
+        } = self;
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            time, position.x, position.y, position.z, pitch, yaw
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraPath {
+    /// At least one, in order of time.
+    keys: Vec<Key>,
+}
+
+impl CameraPath {
+    /// A circle low over the water that climbs, looks down on the waves and
+    /// comes back to the horizon.
+    pub fn builtin() -> Self {
+        let key = |time, [x, y, z]: [f32; 3], pitch, yaw| Key {
+            time,
+            position: glm::vec3(x, y, z),
+            pitch,
+            yaw,
+        };
+        Self {
+            keys: vec![
+                key(0.0, [0.0, 2.0, 0.0], 0.0, 0.0),
+                key(2.5, [-40.0, 4.0, -40.0], -0.1, 0.8),
+                key(5.0, [0.0, 30.0, -80.0], -0.6, 1.6),
+                key(7.5, [40.0, 10.0, -40.0], -0.2, 2.4),
+                key(10.0, [0.0, 2.0, 0.0], 0.0, 3.2),
+            ],
+        }
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut keys: Vec<Key> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let values = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|e| anyhow::anyhow!("line {}: {}", number + 1, e))?;
+            let key = match values[..] {
+                [time, x, y, z, pitch, yaw] => Key {
+                    time,
+                    position: glm::vec3(x, y, z),
+                    pitch,
+                    yaw,
+                },
+                _ => anyhow::bail!(
+                    "line {}: expected 6 values, found {}",
+                    number + 1,
+                    values.len()
+                ),
+            };
+
+            if let Some(last) = keys.last() {
+                anyhow::ensure!(
+                    key.time >= last.time,
+                    "line {}: time goes backwards",
+                    number + 1
+                );
+            }
+            keys.push(key);
+        }
+        anyhow::ensure!(!keys.is_empty(), "empty camera path");
+
+        Ok(Self { keys })
+    }
+
+    /// Seconds from the first key to the last.
+    pub fn duration(&self) -> f32 {
+        self.keys[self.keys.len() - 1].time - self.keys[0].time
+    }
+
+    /// The pose `time` seconds after the first key, linearly interpolated.
+    /// Starts over after the last key.
+    pub fn sample(&self, time: f32) -> Key {
+        let first = self.keys[0];
+        let duration = self.duration();
+        let time = match duration > 0.0 {
+            true => first.time + time.rem_euclid(duration),
+            false => first.time,
+        };
+
+        let next = self
+            .keys
+            .iter()
+            .position(|key| key.time > time)
+            .unwrap_or(self.keys.len() - 1)
+            .max(1)
+            .min(self.keys.len() - 1);
+        let (a, b) = (self.keys[next.saturating_sub(1)], self.keys[next]);
+
+        let span = b.time - a.time;
+        let s = match span > 0.0 {
+            true => ((time - a.time) / span).min(1.0).max(0.0),
+            false => 1.0,
+        };
+        let mix = |a: f32, b: f32| a + (b - a) * s;
+        Key {
+            time,
+            position: glm::mix(&a.position, &b.position, s),
+            pitch: mix(a.pitch, b.pitch),
+            yaw: mix(a.yaw, b.yaw),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_round_trip() {
+        let path = CameraPath::builtin();
+        let text: String =
+            path.keys.iter().map(|key| format!("{}\n", key)).collect();
+        assert_eq!(CameraPath::parse(&text).unwrap(), path);
+
+        assert!(CameraPath::parse("").is_err());
+        assert!(CameraPath::parse("0 1 2 3").is_err());
+        assert!(CameraPath::parse("1 0 0 0 0 0\n0 0 0 0 0 0").is_err());
+    }
+
+    #[test]
+    fn sample_interpolates_and_wraps() {
+        let path = CameraPath::parse(
+            "1 0 0 0 0 0
+             3 2 4 0 1 -1",
+        )
+        .unwrap();
+        assert_eq!(path.duration(), 2.0);
+
+        let middle = path.sample(1.0);
+        assert_eq!(middle.position, glm::vec3(1.0, 2.0, 0.0));
+        assert_eq!((middle.pitch, middle.yaw), (0.5, -0.5));
+
+        assert_eq!(path.sample(2.5), path.sample(0.5));
+        assert_eq!(path.sample(0.0).position, glm::Vec3::zeros());
+    }
+
+    #[test]
+    fn single_key() {
+        let path = CameraPath::parse("4 1 2 3 0 0").unwrap();
+        assert_eq!(path.sample(10.0).position, glm::vec3(1.0, 2.0, 3.0));
+    }
+}
@@ -1,5 +1,6 @@
 use luminance_front::{
     framebuffer::{Framebuffer, FramebufferError},
+    pixel::NormRGBA8UI,
     texture::Dim2,
     Backend,
 };
@@ -37,11 +38,19 @@ impl Surface {
 
         window_context.window().set_cursor_visible(false);
 
+        // Filter across cubemap face edges. Luminance leaves this global
+        // switch alone, so it only needs to be flipped once.
+        unsafe { gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS) };
+
+        let capabilities = Capabilities::detect();
+        tracing::info!("{}", capabilities);
+
         let gl_context = Backend::new().unwrap();
         let shader_preprocessor = crate::shader::Preprocessor::new();
 
         let context = Context {
             shader_preprocessor,
+            capabilities,
             gl_context,
         };
 
@@ -69,13 +78,105 @@ impl Surface {
     }
 }
 
+/// What the driver supports beyond the GL 3.3 core context that is asked for.
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    pub version: (u32, u32),
+    pub renderer: String,
+    /// Tessellation shaders, core since 4.0.
+    pub tessellation: bool,
+    /// Compute shaders and image load/store, core since 4.3. Compute
+    /// programs are written against `#version 430`, so the ARB extensions
+    /// on an older version are not enough.
+    pub compute: bool,
+}
+
+impl Capabilities {
+    /// Query the current context. Tessellation can be turned off with
+    /// `ARCHI_NO_TESSELLATION`, to try the fallback paths on any driver.
+    fn detect() -> Self {
+        let (mut major, mut minor) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+            gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        }
+        let version = (major as u32, minor as u32);
+
+        let renderer = unsafe {
+            let name = gl::GetString(gl::RENDERER);
+            if name.is_null() {
+                String::new()
+            } else {
+                std::ffi::CStr::from_ptr(name as *const _)
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        };
+
+        let extensions: Vec<String> = unsafe {
+            let mut count = 0;
+            gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+            (0..count as u32)
+                .filter_map(|i| {
+                    let name = gl::GetStringi(gl::EXTENSIONS, i);
+                    if name.is_null() {
+                        return None;
+                    }
+                    let name = std::ffi::CStr::from_ptr(name as *const _);
+                    Some(name.to_string_lossy().into_owned())
+                })
+                .collect()
+        };
+        let has = |name: &str| extensions.iter().any(|e| e == name);
+
+        let tessellation = (version >= (4, 0)
+            || has("GL_ARB_tessellation_shader"))
+            && std::env::var_os("ARCHI_NO_TESSELLATION").is_none();
+        let compute = version >= (4, 3);
+
+        Self {
+            version,
+            renderer,
+            tessellation,
+            compute,
+        }
+    }
+}
+
+impl std::fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (major, minor) = self.version;
+        write!(f, "OpenGL {}.{} on {}", major, minor, self.renderer)?;
+        for (name, supported) in &[
+            ("tessellation", self.tessellation),
+            ("compute", self.compute),
+        ] {
+            if !supported {
+                write!(f, ", no {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 pub struct Context {
     pub shader_preprocessor: crate::shader::Preprocessor,
+    pub capabilities: Capabilities,
     gl_context: Backend,
 }
 
 pub type BackBuffer = Framebuffer<Dim2, (), ()>;
 
+/// Stands in for the back buffer when rendering without showing anything.
+pub type OffscreenBuffer = Framebuffer<Dim2, NormRGBA8UI, ()>;
+
+/// Where finished frames go.
+#[derive(Clone, Copy)]
+pub enum Output<'a> {
+    BackBuffer(&'a BackBuffer),
+    Offscreen(&'a OffscreenBuffer),
+}
+
 impl Context {
     /// Get access to the back buffer.
     pub fn back_buffer(
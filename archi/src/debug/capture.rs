@@ -0,0 +1,179 @@
+//! Names for GL objects and for groups of commands, which frame capture
+//! tools like RenderDoc show in place of numbers. Needs GL 4.3 or
+//! `GL_KHR_debug`, without which nothing is named.
+//!
+//! Luminance does not hand out the names of its objects, so they are found by
+//! getting luminance to bind them in a pipeline on a throwaway framebuffer.
+
+use crate::context::Context;
+use gl::types::{GLchar, GLenum, GLint, GLsizei, GLuint};
+use luminance::backend::{color_slot::ColorSlot, depth_slot::DepthSlot};
+use luminance::texture::{Dim, Dimensionable};
+use luminance_front::{
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pipeline::{Pipeline, PipelineState},
+    pixel::{Pixel, R32F},
+    shader::Program,
+    shading_gate::ShadingGate,
+    texture::{Dim2, Texture},
+    vertex::Semantics,
+    Backend,
+};
+
+fn supported() -> bool {
+    gl::ObjectLabel::is_loaded() && gl::PushDebugGroup::is_loaded()
+}
+
+/// Commands between its creation and drop, shown as one collapsible group.
+#[must_use]
+pub struct DebugGroup {
+    pushed: bool,
+}
+
+impl DebugGroup {
+    pub fn new(name: &str) -> Self {
+        let pushed = supported();
+        if pushed {
+            unsafe {
+                gl::PushDebugGroup(
+                    gl::DEBUG_SOURCE_APPLICATION,
+                    0,
+                    name.len() as GLsizei,
+                    name.as_ptr() as *const GLchar,
+                );
+            }
+        }
+        Self { pushed }
+    }
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        if self.pushed {
+            unsafe { gl::PopDebugGroup() };
+        }
+    }
+}
+
+fn object_label(identifier: GLenum, name: GLuint, label: &str) {
+    unsafe {
+        gl::ObjectLabel(
+            identifier,
+            name,
+            label.len() as GLsizei,
+            label.as_ptr() as *const GLchar,
+        );
+    }
+}
+
+/// Run `f` in a pipeline, on a framebuffer it leaves as it was.
+fn in_pipeline<CS, DS>(
+    context: &mut Context,
+    framebuffer: &Framebuffer<Dim2, CS, DS>,
+    f: impl for<'a> FnOnce(Pipeline<'a>, ShadingGate<'a>) -> anyhow::Result<()>,
+) -> anyhow::Result<()>
+where
+    CS: ColorSlot<Backend, Dim2>,
+    DS: DepthSlot<Backend, Dim2>,
+{
+    let state = PipelineState::new()
+        .enable_clear_color(false)
+        .enable_clear_depth(false);
+    context
+        .new_pipeline_gate()
+        .pipeline(framebuffer, &state, f)
+        .into_result()
+}
+
+fn scratch(
+    context: &mut Context,
+) -> anyhow::Result<Framebuffer<Dim2, R32F, ()>> {
+    Ok(context.new_framebuffer([1, 1], 0, Default::default())?)
+}
+
+pub fn label_texture<D, P>(
+    context: &mut Context,
+    texture: &mut Texture<D, P>,
+    label: &str,
+) -> anyhow::Result<()>
+where
+    D: Dimensionable,
+    P: Pixel,
+{
+    if !supported() {
+        return Ok(());
+    }
+
+    let binding = match D::dim() {
+        Dim::Dim1 => gl::TEXTURE_BINDING_1D,
+        Dim::Dim2 => gl::TEXTURE_BINDING_2D,
+        Dim::Dim3 => gl::TEXTURE_BINDING_3D,
+        Dim::Cubemap => gl::TEXTURE_BINDING_CUBE_MAP,
+        Dim::Dim1Array => gl::TEXTURE_BINDING_1D_ARRAY,
+        Dim::Dim2Array => gl::TEXTURE_BINDING_2D_ARRAY,
+    };
+
+    let framebuffer = scratch(context)?;
+    in_pipeline(context, &framebuffer, |pipeline, _| {
+        let bound = pipeline.bind_texture(texture)?;
+        let name = crate::shader::bound_texture_name_of(
+            bound.binding().binding(),
+            binding,
+        );
+        object_label(gl::TEXTURE, name, label);
+        Ok(())
+    })
+}
+
+/// Label a framebuffer, but not the textures attached to it.
+pub fn label_framebuffer<CS, DS>(
+    context: &mut Context,
+    framebuffer: &Framebuffer<Dim2, CS, DS>,
+    label: &str,
+) -> anyhow::Result<()>
+where
+    CS: ColorSlot<Backend, Dim2>,
+    DS: DepthSlot<Backend, Dim2>,
+{
+    if !supported() {
+        return Ok(());
+    }
+
+    in_pipeline(context, framebuffer, |_, _| {
+        let mut name: GLint = 0;
+        unsafe { gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut name) };
+        object_label(gl::FRAMEBUFFER, name as GLuint, label);
+        Ok(())
+    })
+}
+
+pub fn label_program<Sem, Out, Uni>(
+    context: &mut Context,
+    program: &mut Program<Sem, Out, Uni>,
+    label: &str,
+) -> anyhow::Result<()>
+where
+    Sem: Semantics,
+{
+    if !supported() {
+        return Ok(());
+    }
+
+    let framebuffer = scratch(context)?;
+    in_pipeline(context, &framebuffer, |_, mut shader_gate| {
+        shader_gate.shade(program, |_, _, _| {
+            let mut name: GLint = 0;
+            unsafe { gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut name) };
+            object_label(gl::PROGRAM, name as GLuint, label);
+            Ok(())
+        })
+    })
+}
+
+/// Label a program that is not luminance's.
+pub fn label_program_name(name: GLuint, label: &str) {
+    if supported() {
+        object_label(gl::PROGRAM, name, label);
+    }
+}
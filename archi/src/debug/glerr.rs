@@ -1,5 +1,14 @@
+//! OpenGL errors and debug messages, reported through `tracing` with the
+//! target `gl`, so `RUST_LOG=gl=debug` shows everything the driver says.
 #![allow(dead_code)]
 
+use gl::types::*;
+use std::collections::HashSet;
+use std::convert::TryInto;
+use std::sync::Mutex;
+
+const TARGET: &str = "gl";
+
 fn error_string(error: gl::types::GLuint) -> &'static str {
     match error {
         gl::NO_ERROR => "GL_NO_ERROR",
@@ -27,7 +36,7 @@ pub fn print_gl_errors() -> bool {
     let mut any_error = false;
     while let Some(error) = get_error() {
         any_error = true;
-        eprintln!("GL error: {}", error);
+        tracing::error!(target: TARGET, "{}", error);
     }
     any_error
 }
@@ -48,7 +57,6 @@ pub enum GlDebugSeverity {
     Notification = gl::DEBUG_SEVERITY_NOTIFICATION,
 }
 
-use std::convert::TryInto;
 impl std::convert::TryFrom<GLenum> for GlDebugSeverity {
     type Error = ();
 
@@ -63,6 +71,44 @@ impl std::convert::TryFrom<GLenum> for GlDebugSeverity {
     }
 }
 
+impl GlDebugSeverity {
+    pub fn next(self) -> Self {
+        match self {
+            Self::High => Self::Notification,
+            Self::Medium => Self::High,
+            Self::Low => Self::Medium,
+            Self::Notification => Self::Low,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::High => "high",
+            Self::Medium => "medium",
+            Self::Low => "low",
+            Self::Notification => "notification",
+        }
+    }
+}
+
+impl std::str::FromStr for GlDebugSeverity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "high" => Ok(Self::High),
+            "medium" => Ok(Self::Medium),
+            "low" => Ok(Self::Low),
+            "notification" => Ok(Self::Notification),
+            _ => Err(anyhow::anyhow!(
+                "Unknown debug message severity {:?}, expected high, \
+                 medium, low or notification",
+                s
+            )),
+        }
+    }
+}
+
 impl Ord for GlDebugSeverity {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         let numeric = |&severity| match severity {
@@ -82,46 +128,38 @@ impl PartialOrd for GlDebugSeverity {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct UserParam {
+/// What the callback lets through, and what it found.
+#[derive(Debug)]
+struct State {
     minimum_severity: GlDebugSeverity,
+    muted: HashSet<GLuint>,
+    panic_on_high: bool,
+    /// The first high severity message while `panic_on_high`, with a
+    /// backtrace of the GL call that caused it.
+    failure: Option<String>,
 }
 
-use gl::types::*;
-extern "system" fn message_callback(
-    source: GLenum,
-    gltype: GLenum,
-    _id: GLuint,
-    severity: GLenum,
-    length: GLsizei,
-    message: *const GLchar,
-    user_param: *mut GLvoid,
-) {
-    let UserParam { minimum_severity } = unsafe { *(user_param as *const _) };
-
-    let severity = match severity.try_into() {
-        Ok(severity) if severity >= minimum_severity => severity,
-        _ => {
-            return;
-        }
-    };
+impl State {
+    fn admits(&self, severity: GlDebugSeverity, id: GLuint) -> bool {
+        severity >= self.minimum_severity && !self.muted.contains(&id)
+    }
+}
 
-    let fallback;
-    let source = match source {
+fn source_name(source: GLenum) -> String {
+    match source {
         gl::DEBUG_SOURCE_API => "API",
         gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
         gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
         gl::DEBUG_SOURCE_APPLICATION => "application",
         gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
-        gl::DEBUG_SOURCE_OTHER => "unspecified source",
-        _ => {
-            fallback = format!("Unknown message source {}", source);
-            &fallback
-        }
-    };
+        gl::DEBUG_SOURCE_OTHER => "other",
+        _ => return format!("unknown ({})", source),
+    }
+    .to_string()
+}
 
-    let fallback;
-    let gltype = match gltype {
+fn type_name(gltype: GLenum) -> String {
+    match gltype {
         gl::DEBUG_TYPE_ERROR => "error",
         gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
         gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
@@ -130,49 +168,221 @@ extern "system" fn message_callback(
         gl::DEBUG_TYPE_MARKER => "marker",
         gl::DEBUG_TYPE_PUSH_GROUP => "push debug group",
         gl::DEBUG_TYPE_POP_GROUP => "pop debug group",
-        gl::DEBUG_TYPE_OTHER => "GL_DEBUG_TYPE_OTHER",
-        _ => {
-            fallback = format!("{{Unknown message type {}}}", gltype);
-            &fallback
-        }
+        gl::DEBUG_TYPE_OTHER => "other",
+        _ => return format!("unknown ({})", gltype),
+    }
+    .to_string()
+}
+
+extern "system" fn message_callback(
+    source: GLenum,
+    gltype: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    user_param: *mut GLvoid,
+) {
+    let state = unsafe { &*(user_param as *const Mutex<State>) };
+    let mut state = match state.lock() {
+        Ok(state) => state,
+        // Poisoned by a panic elsewhere, nothing to report to
+        Err(_) => return,
     };
 
-    let severity = match severity {
-        GlDebugSeverity::High => "high",
-        GlDebugSeverity::Medium => "medium",
-        GlDebugSeverity::Low => "low",
-        GlDebugSeverity::Notification => "notification",
+    let severity: GlDebugSeverity = match severity.try_into() {
+        Ok(severity) if state.admits(severity, id) => severity,
+        _ => return,
     };
 
-    use std::ffi::*;
     let message = unsafe {
-        if length < 0 {
-            CStr::from_ptr(message)
-        } else {
-            let slice = std::slice::from_raw_parts(
-                message as *const u8,
-                length as usize + 1,
-            );
-            CStr::from_bytes_with_nul(slice).unwrap()
-        }
+        let length = match length < 0 {
+            true => std::ffi::CStr::from_ptr(message).to_bytes().len(),
+            false => length as usize,
+        };
+        std::slice::from_raw_parts(message as *const u8, length)
     };
+    let message = String::from_utf8_lossy(message);
+    let message = message.trim_end_matches(&['\0', '\n'][..]);
+
+    let source = source_name(source);
+    let kind = type_name(gltype);
+    macro_rules! event {
+        ($level:expr) => {
+            tracing::event!(
+                target: TARGET,
+                $level,
+                source = source.as_str(),
+                kind = kind.as_str(),
+                severity = severity.name(),
+                id,
+                "{}",
+                message
+            )
+        };
+    }
+    match severity {
+        GlDebugSeverity::High => event!(tracing::Level::ERROR),
+        GlDebugSeverity::Medium => event!(tracing::Level::WARN),
+        GlDebugSeverity::Low => event!(tracing::Level::INFO),
+        GlDebugSeverity::Notification => event!(tracing::Level::DEBUG),
+    }
+
+    // Unwinding into the driver is undefined, so the panic waits for `check`
+    if severity == GlDebugSeverity::High
+        && state.panic_on_high
+        && state.failure.is_none()
+    {
+        state.failure = Some(format!(
+            "{} {} message {} from {}: {}\n{:?}",
+            severity.name(),
+            kind,
+            id,
+            source,
+            message,
+            backtrace::Backtrace::new()
+        ));
+    }
+}
+
+/// Debug messages of the current context, reported while this lives.
+///
+/// Messages are synchronous, so they arrive during the GL call that caused
+/// them and a backtrace from the callback leads back to it.
+pub struct DebugMessages {
+    /// Shared with the callback, which is given its address.
+    state: Box<Mutex<State>>,
+}
+
+impl DebugMessages {
+    pub fn new(minimum_severity: GlDebugSeverity) -> Self {
+        let state = Box::new(Mutex::new(State {
+            minimum_severity,
+            muted: HashSet::new(),
+            panic_on_high: false,
+            failure: None,
+        }));
+
+        if gl::DebugMessageCallback::is_loaded() {
+            unsafe {
+                gl::Enable(gl::DEBUG_OUTPUT);
+                gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+                gl::DebugMessageCallback(
+                    Some(message_callback),
+                    &*state as *const Mutex<State> as *const GLvoid,
+                );
+            }
+        }
 
-    eprintln!(
-        "{} severity debug message about {} from {}: {}",
-        severity,
-        gltype,
-        source,
-        message.to_string_lossy(),
-    );
-}
-
-#[allow(unused)]
-pub fn debug_messages(minimum_severity: GlDebugSeverity) {
-    unsafe {
-        let user_param = Box::leak(Box::new(UserParam { minimum_severity }));
-        gl::DebugMessageCallback(
-            Some(message_callback),
-            user_param as *const _ as *const _,
+        Self { state }
+    }
+
+    /// Set up from the environment:
+    ///
+    /// - `ARCHI_GL_DEBUG`: the minimum severity, `low` if unset
+    /// - `ARCHI_GL_DEBUG_MUTE`: comma separated ids of messages to drop
+    /// - `ARCHI_GL_DEBUG_PANIC`: if set, `check` panics after a high severity
+    ///   message
+    pub fn from_env() -> anyhow::Result<Self> {
+        use anyhow::Context as _;
+
+        let minimum_severity = match std::env::var("ARCHI_GL_DEBUG") {
+            Ok(severity) => severity.parse().context("ARCHI_GL_DEBUG")?,
+            Err(_) => GlDebugSeverity::Low,
+        };
+        let messages = Self::new(minimum_severity);
+
+        if let Ok(ids) = std::env::var("ARCHI_GL_DEBUG_MUTE") {
+            for id in ids.split(',').map(str::trim).filter(|id| !id.is_empty())
+            {
+                messages.mute(id.parse().with_context(|| {
+                    format!("ARCHI_GL_DEBUG_MUTE: bad message id {:?}", id)
+                })?);
+            }
+        }
+        messages.set_panic_on_high(
+            std::env::var_os("ARCHI_GL_DEBUG_PANIC").is_some(),
         );
+
+        Ok(messages)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn minimum_severity(&self) -> GlDebugSeverity {
+        self.state().minimum_severity
+    }
+
+    pub fn set_minimum_severity(&self, severity: GlDebugSeverity) {
+        self.state().minimum_severity = severity;
+    }
+
+    /// Drop messages with `id`, for known noise from a driver.
+    pub fn mute(&self, id: GLuint) {
+        self.state().muted.insert(id);
+    }
+
+    pub fn unmute(&self, id: GLuint) {
+        self.state().muted.remove(&id);
+    }
+
+    pub fn set_panic_on_high(&self, panic: bool) {
+        self.state().panic_on_high = panic;
+    }
+
+    /// Panic if a high severity message arrived while panicking on them is
+    /// enabled.
+    #[track_caller]
+    pub fn check(&self) {
+        if let Some(failure) = self.state().failure.take() {
+            panic!("OpenGL debug message: {}", failure);
+        }
+    }
+}
+
+impl Drop for DebugMessages {
+    fn drop(&mut self) {
+        // The callback must not outlive its state
+        if gl::DebugMessageCallback::is_loaded() {
+            unsafe { gl::DebugMessageCallback(None, std::ptr::null()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filters_by_severity_and_id() {
+        let mut state = State {
+            minimum_severity: GlDebugSeverity::Medium,
+            muted: HashSet::new(),
+            panic_on_high: false,
+            failure: None,
+        };
+        assert!(state.admits(GlDebugSeverity::High, 1));
+        assert!(state.admits(GlDebugSeverity::Medium, 1));
+        assert!(!state.admits(GlDebugSeverity::Low, 1));
+
+        state.muted.insert(1);
+        assert!(!state.admits(GlDebugSeverity::High, 1));
+        assert!(state.admits(GlDebugSeverity::High, 2));
+    }
+
+    #[test]
+    fn severity_names() {
+        let mut severity = GlDebugSeverity::Notification;
+        for _ in 0..4 {
+            assert_eq!(
+                severity.name().parse::<GlDebugSeverity>().unwrap(),
+                severity
+            );
+            severity = severity.next();
+        }
+        assert_eq!(severity, GlDebugSeverity::Notification);
+        assert!("loud".parse::<GlDebugSeverity>().is_err());
     }
 }
@@ -0,0 +1,712 @@
+//! A full screen view of one texture at a time, chosen from the textures the
+//! renderer offers each frame.
+//!
+//! - `I` opens and closes it, as does `Escape`
+//! - `Page Up` and `Page Down` choose the texture
+//! - the wheel zooms around the cursor, dragging with the left button pans
+//!   and `Home` fits the texture to the screen
+//! - `C` cycles through showing red, green and blue or a single channel
+//! - `[` and `]` narrow and widen the range of values from black to white,
+//!   `\` switches it between starting at zero and centred on zero
+//! - `,` and `.` choose the layer of an array, or the face of a cubemap
+//!
+//! The value of the texel under the cursor is read back and shown in the
+//! bottom left corner.
+
+use crate::context::{BackBuffer, Context};
+use glutin::event::{
+    ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
+use luminance::blending::{Blending, Equation, Factor};
+use luminance_derive::UniformInterface;
+use luminance_front::{
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pipeline::{Pipeline, PipelineGate, PipelineState, TextureBinding},
+    pixel::{Floating, Pixel, R32F, RGBA32F},
+    render_state::RenderState,
+    shader::{Program, Uniform},
+    shading_gate::ShadingGate,
+    tess::Tess,
+    texture::{
+        Cubemap, Dim2, Dim2Array, GenMipmaps, MagFilter, MinFilter, Sampler,
+        Texture,
+    },
+};
+use std::time::{Duration, Instant};
+
+/// The least time between two texel values read back.
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Characters the readout can show, in the order of the font in
+/// inspector_readout.frag. Anything else is shown as a space.
+const GLYPHS: &str = " 0123456789.-+,abefgilnr";
+
+/// Characters per line of the readout, enough for a channel name and any
+/// `f32` in scientific notation.
+const READOUT_COLUMNS: usize = 13;
+/// The texel, then a line per channel.
+const READOUT_LINES: usize = 5;
+/// Window pixels per pixel of the readout font.
+const READOUT_SCALE: f32 = 3.0;
+
+/// Zoom per line of the mouse wheel.
+const ZOOM_STEP: f32 = 1.25;
+
+#[derive(UniformInterface)]
+pub struct InspectorInterface {
+    #[uniform(unbound)]
+    texture_2d: Uniform<TextureBinding<Dim2, Floating>>,
+    #[uniform(unbound)]
+    texture_array: Uniform<TextureBinding<Dim2Array, Floating>>,
+    #[uniform(unbound)]
+    texture_cube: Uniform<TextureBinding<Cubemap, Floating>>,
+
+    half_viewport: Uniform<[f32; 2]>,
+    center: Uniform<[f32; 2]>,
+    zoom: Uniform<f32>,
+    size: Uniform<[i32; 2]>,
+    #[uniform(unbound)]
+    layer: Uniform<i32>,
+    channel: Uniform<i32>,
+    range: Uniform<[f32; 2]>,
+    raw: Uniform<bool>,
+    hovered: Uniform<[i32; 2]>,
+}
+
+type InspectorShader = Program<(), (), InspectorInterface>;
+
+#[derive(UniformInterface)]
+struct ReadoutInterface {
+    text: Uniform<TextureBinding<Dim2, Floating>>,
+    origin: Uniform<[f32; 2]>,
+    scale: Uniform<f32>,
+}
+
+/// A program for each kind of texture, which need different samplers.
+pub struct Shaders {
+    flat: InspectorShader,
+    array: InspectorShader,
+    cube: InspectorShader,
+}
+
+/// A texture the inspector can show.
+pub trait Inspect {
+    /// Size of a layer, and the number of layers.
+    fn extent(&self) -> ([u32; 2], u32);
+
+    fn render(
+        &mut self,
+        pipeline: &Pipeline,
+        shader_gate: &mut ShadingGate,
+        shaders: &mut Shaders,
+        quad: &Tess<()>,
+        uniforms: &Uniforms,
+    ) -> anyhow::Result<()>;
+}
+
+/// A texture offered to the inspector, by name.
+pub type Inspectable<'a> = (&'static str, &'a mut dyn Inspect);
+
+macro_rules! impl_inspect {
+    ($dim:ty, $shader:ident, $sampler:ident, $extent:expr) => {
+        impl<P> Inspect for Texture<$dim, P>
+        where
+            P: Pixel<SamplerType = Floating>,
+        {
+            fn extent(&self) -> ([u32; 2], u32) {
+                ($extent)(self.size())
+            }
+
+            fn render(
+                &mut self,
+                pipeline: &Pipeline,
+                shader_gate: &mut ShadingGate,
+                shaders: &mut Shaders,
+                quad: &Tess<()>,
+                uniforms: &Uniforms,
+            ) -> anyhow::Result<()> {
+                let texture = pipeline.bind_texture(self)?;
+                shader_gate.shade(
+                    &mut shaders.$shader,
+                    |mut iface, uni, mut render_gate| {
+                        iface.set(&uni.$sampler, texture.binding());
+                        iface.set(&uni.half_viewport, uniforms.half_viewport);
+                        iface.set(&uni.center, uniforms.center);
+                        iface.set(&uni.zoom, uniforms.zoom);
+                        iface.set(&uni.size, uniforms.size);
+                        iface.set(&uni.layer, uniforms.layer);
+                        iface.set(&uni.channel, uniforms.channel);
+                        iface.set(&uni.range, uniforms.range);
+                        iface.set(&uni.raw, uniforms.raw);
+                        iface.set(&uni.hovered, uniforms.hovered);
+
+                        let state = RenderState::default().set_depth_test(None);
+                        render_gate.render(&state, |mut tess_gate| {
+                            tess_gate.render(quad)
+                        })
+                    },
+                )
+            }
+        }
+    };
+}
+
+impl_inspect!(Dim2, flat, texture_2d, |size| (size, 1));
+impl_inspect!(Dim2Array, array, texture_array, |(size, layers)| (
+    size, layers
+));
+impl_inspect!(Cubemap, cube, texture_cube, |size| ([size, size], 6));
+
+/// Everything the shaders are told about how to show a texture.
+pub struct Uniforms {
+    half_viewport: [f32; 2],
+    center: [f32; 2],
+    zoom: f32,
+    size: [i32; 2],
+    layer: i32,
+    channel: i32,
+    range: [f32; 2],
+    raw: bool,
+    hovered: [i32; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channels {
+    Rgb,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl Channels {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Rgb => Self::Red,
+            Self::Red => Self::Green,
+            Self::Green => Self::Blue,
+            Self::Blue => Self::Alpha,
+            Self::Alpha => Self::Rgb,
+        }
+    }
+
+    fn index(self) -> i32 {
+        match self {
+            Self::Rgb => -1,
+            Self::Red => 0,
+            Self::Green => 1,
+            Self::Blue => 2,
+            Self::Alpha => 3,
+        }
+    }
+}
+
+/// Where the texture is on screen. Positions on screen are in window pixels
+/// from the bottom left corner, positions in the texture are in texels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct View {
+    /// Texture position in the middle of the viewport.
+    center: glm::Vec2,
+    /// Window pixels per texel.
+    zoom: f32,
+}
+
+impl View {
+    /// The whole texture, as large as fits.
+    fn fit(size: [u32; 2], viewport: [u32; 2]) -> Self {
+        let size = glm::vec2(size[0] as f32, size[1] as f32);
+        let viewport = glm::vec2(viewport[0] as f32, viewport[1] as f32);
+        let zoom = (viewport.x / size.x).min(viewport.y / size.y) * 0.9;
+        Self {
+            center: size / 2.0,
+            zoom,
+        }
+    }
+
+    fn texture_position(
+        &self,
+        pixel: glm::Vec2,
+        viewport: [u32; 2],
+    ) -> glm::Vec2 {
+        let half_viewport =
+            glm::vec2(viewport[0] as f32, viewport[1] as f32) / 2.0;
+        self.center + (pixel - half_viewport) / self.zoom
+    }
+
+    /// Zoom by `factor`, keeping what is under `pixel` in place.
+    fn zoom_at(&mut self, pixel: glm::Vec2, viewport: [u32; 2], factor: f32) {
+        let fixed = self.texture_position(pixel, viewport);
+        self.zoom *= factor;
+        self.center += fixed - self.texture_position(pixel, viewport);
+    }
+
+    fn pan(&mut self, pixels: glm::Vec2) {
+        self.center -= pixels / self.zoom;
+    }
+}
+
+/// Lines of text giving `value` of `texel`.
+fn readout_lines(texel: [i32; 2], value: [f32; 4]) -> Vec<String> {
+    let mut lines = vec![format!("{},{}", texel[0], texel[1])];
+    for (channel, value) in "rgba".chars().zip(&value) {
+        lines.push(format!("{} {:.4e}", channel, value).to_ascii_lowercase());
+    }
+    lines
+}
+
+/// The readout texture for `lines`, as indices into `GLYPHS`.
+fn readout_glyphs(lines: &[String]) -> Vec<f32> {
+    let mut glyphs = vec![0.0; READOUT_COLUMNS * READOUT_LINES];
+    for (row, line) in glyphs.chunks_mut(READOUT_COLUMNS).zip(lines) {
+        for (glyph, c) in row.iter_mut().zip(line.chars()) {
+            *glyph = GLYPHS.find(c).unwrap_or(0) as f32;
+        }
+    }
+    glyphs
+}
+
+pub struct Inspector {
+    open: bool,
+    selected: usize,
+    /// Textures offered in the last frame.
+    count: usize,
+    /// None until the texture is fit to the screen in the next frame.
+    view: Option<View>,
+    layer: u32,
+    channels: Channels,
+    range: [f32; 2],
+    /// In window pixels from the top left, as the window reports it.
+    cursor: Option<glm::Vec2>,
+    dragging: bool,
+    /// The texture, layer and texel whose value was read back last.
+    probed: Option<(usize, u32, [i32; 2])>,
+    last_probe: Instant,
+    /// The texel last read back and its value.
+    readout: Option<([i32; 2], [f32; 4])>,
+    shaders: Shaders,
+    quad: Tess<()>,
+    probe: Framebuffer<Dim2, RGBA32F, ()>,
+    readout_text: Texture<Dim2, R32F>,
+    readout_shader: Program<(), (), ReadoutInterface>,
+}
+
+impl Inspector {
+    pub fn new(context: &mut Context) -> anyhow::Result<Self> {
+        let mut shader = |frag| {
+            crate::shader::from_sources(
+                context,
+                None,
+                crate::postprocess::QUAD_VS_SRC,
+                None,
+                frag,
+            )
+        };
+        let shaders = Shaders {
+            flat: shader(crate::shader_source!("./shaders/inspector_2d.frag"))?,
+            array: shader(crate::shader_source!(
+                "./shaders/inspector_array.frag"
+            ))?,
+            cube: shader(crate::shader_source!(
+                "./shaders/inspector_cube.frag"
+            ))?,
+        };
+
+        let readout_shader =
+            shader(crate::shader_source!("./shaders/inspector_readout.frag"))?;
+        let sampler = Sampler {
+            mag_filter: MagFilter::Nearest,
+            min_filter: MinFilter::Nearest,
+            ..Default::default()
+        };
+        let readout_text = context.new_texture(
+            [READOUT_COLUMNS as u32, READOUT_LINES as u32],
+            0,
+            sampler,
+        )?;
+
+        Ok(Self {
+            open: false,
+            selected: 0,
+            count: 0,
+            view: None,
+            layer: 0,
+            channels: Channels::Rgb,
+            range: [0.0, 1.0],
+            cursor: None,
+            dragging: false,
+            probed: None,
+            last_probe: Instant::now(),
+            readout: None,
+            shaders,
+            quad: crate::postprocess::quad(context)?,
+            probe: context.new_framebuffer([1, 1], 0, Default::default())?,
+            readout_text,
+            readout_shader,
+        })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.dragging = false;
+        tracing::info!(open = self.open, "inspector");
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        self.view = None;
+        self.layer = 0;
+        self.probed = None;
+        self.readout = None;
+    }
+
+    /// Handle an event while open, returning whether it was used.
+    pub fn input(&mut self, event: &Event<()>, viewport: [u32; 2]) -> bool {
+        let event = match event {
+            Event::WindowEvent { event, .. } if self.open => event,
+            _ => return false,
+        };
+
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = glm::vec2(position.x as f32, position.y as f32);
+                if let (true, Some(cursor), Some(view)) =
+                    (self.dragging, self.cursor, &mut self.view)
+                {
+                    let moved = position - cursor;
+                    view.pan(glm::vec2(moved.x, -moved.y));
+                }
+                self.cursor = Some(position);
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor = None,
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.dragging = *state == ElementState::Pressed;
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_x, y) => *y,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / 100.0,
+                };
+                if let (Some(pixel), Some(view)) =
+                    (self.cursor_pixel(viewport), &mut self.view)
+                {
+                    view.zoom_at(pixel, viewport, ZOOM_STEP.powf(lines));
+                }
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Released,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => return self.key(*key),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn key(&mut self, key: VirtualKeyCode) -> bool {
+        let count = self.count.max(1);
+        match key {
+            VirtualKeyCode::Escape => self.toggle(),
+            VirtualKeyCode::PageDown => {
+                self.select((self.selected + 1) % count)
+            }
+            VirtualKeyCode::PageUp => {
+                self.select((self.selected + count - 1) % count)
+            }
+            VirtualKeyCode::Home => self.view = None,
+            VirtualKeyCode::C => {
+                self.channels = self.channels.next();
+                tracing::info!(channels = ?self.channels, "inspector");
+            }
+            VirtualKeyCode::LBracket | VirtualKeyCode::RBracket => {
+                let step = match key {
+                    VirtualKeyCode::LBracket => 0.5,
+                    _ => 2.0,
+                };
+                self.range = [self.range[0] * step, self.range[1] * step];
+                tracing::info!(range = ?self.range, "inspector");
+            }
+            VirtualKeyCode::Backslash => {
+                let [min, max] = self.range;
+                self.range = match min < 0.0 {
+                    true => [0.0, max],
+                    false => [-max, max],
+                };
+                tracing::info!(range = ?self.range, "inspector");
+            }
+            VirtualKeyCode::Comma => self.layer = self.layer.saturating_sub(1),
+            VirtualKeyCode::Period => self.layer += 1,
+            _ => return false,
+        }
+        true
+    }
+
+    fn cursor_pixel(&self, viewport: [u32; 2]) -> Option<glm::Vec2> {
+        self.cursor
+            .map(|cursor| glm::vec2(cursor.x, viewport[1] as f32 - cursor.y))
+    }
+
+    /// Draw the selected one of `textures` over all of `back_buffer`, of size
+    /// `viewport`, if open.
+    pub fn render(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        back_buffer: &BackBuffer,
+        viewport: [u32; 2],
+        textures: &mut [Inspectable],
+    ) -> anyhow::Result<()> {
+        self.count = textures.len();
+        if !self.open || textures.is_empty() {
+            return Ok(());
+        }
+        if self.selected >= textures.len() {
+            self.select(0);
+        }
+
+        let (name, texture) = &mut textures[self.selected];
+        let (size, layers) = texture.extent();
+        self.layer = self.layer.min(layers - 1);
+        let view = match self.view {
+            Some(view) => view,
+            None => {
+                tracing::info!(
+                    texture = %name,
+                    width = size[0],
+                    height = size[1],
+                    layers,
+                    "inspecting"
+                );
+                let view = View::fit(size, viewport);
+                self.view = Some(view);
+                view
+            }
+        };
+
+        let hovered = self.cursor_pixel(viewport).and_then(|pixel| {
+            let position = view.texture_position(pixel, viewport);
+            let texel = [position.x.floor() as i32, position.y.floor() as i32];
+            let inside =
+                (0..2).all(|i| (0..size[i] as i32).contains(&texel[i]));
+            match inside {
+                true => Some(texel),
+                false => None,
+            }
+        });
+
+        let mut uniforms = Uniforms {
+            half_viewport: [viewport[0] as f32 / 2.0, viewport[1] as f32 / 2.0],
+            center: view.center.into(),
+            zoom: view.zoom,
+            size: [size[0] as i32, size[1] as i32],
+            layer: self.layer as i32,
+            channel: self.channels.index(),
+            range: self.range,
+            raw: false,
+            hovered: hovered.unwrap_or([-1, -1]),
+        };
+
+        let Self {
+            shaders,
+            quad,
+            probe,
+            ..
+        } = self;
+        let state = PipelineState::new().enable_clear_color(false);
+        pipeline_gate
+            .pipeline(
+                back_buffer,
+                &state,
+                |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                    texture.render(
+                        &pipeline,
+                        &mut shader_gate,
+                        shaders,
+                        quad,
+                        &uniforms,
+                    )
+                },
+            )
+            .into_result()?;
+
+        let texel = match hovered {
+            Some(texel) => texel,
+            None => return Ok(()),
+        };
+
+        // Read back the texel under the cursor, at most so often since it
+        // waits for the GPU
+        let key = (self.selected, self.layer, texel);
+        if self.probed != Some(key)
+            && self.last_probe.elapsed() >= PROBE_INTERVAL
+        {
+            uniforms.half_viewport = [0.5, 0.5];
+            uniforms.center = [texel[0] as f32 + 0.5, texel[1] as f32 + 0.5];
+            uniforms.zoom = 1.0;
+            uniforms.raw = true;
+            pipeline_gate
+                .pipeline(
+                    &*probe,
+                    &PipelineState::new(),
+                    |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                        texture.render(
+                            &pipeline,
+                            &mut shader_gate,
+                            shaders,
+                            quad,
+                            &uniforms,
+                        )
+                    },
+                )
+                .into_result()?;
+            let value = probe.color_slot().get_raw_texels()?;
+
+            self.readout =
+                Some((texel, [value[0], value[1], value[2], value[3]]));
+            self.probed = Some(key);
+            self.last_probe = Instant::now();
+        }
+
+        self.render_readout(pipeline_gate, back_buffer)
+    }
+
+    /// Draw the last value read back in the bottom left corner.
+    fn render_readout(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        back_buffer: &BackBuffer,
+    ) -> anyhow::Result<()> {
+        let Self {
+            readout,
+            readout_text,
+            readout_shader,
+            quad,
+            ..
+        } = self;
+        let (texel, value) = match readout {
+            Some(readout) => *readout,
+            None => return Ok(()),
+        };
+        readout_text.upload(
+            GenMipmaps::No,
+            &readout_glyphs(&readout_lines(texel, value)),
+        )?;
+
+        pipeline_gate
+            .pipeline(
+                back_buffer,
+                &PipelineState::new().enable_clear_color(false),
+                |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                    let text = pipeline.bind_texture(readout_text)?;
+                    shader_gate.shade(
+                        readout_shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface.set(&uni.text, text.binding());
+                            iface.set(&uni.origin, [8.0, 8.0]);
+                            iface.set(&uni.scale, READOUT_SCALE);
+
+                            let state = RenderState::default()
+                                .set_depth_test(None)
+                                .set_blending(Blending {
+                                    equation: Equation::Additive,
+                                    src: Factor::SrcAlpha,
+                                    dst: Factor::SrcAlphaComplement,
+                                });
+                            render_gate.render(&state, |mut tess_gate| {
+                                tess_gate.render(&*quad)
+                            })
+                        },
+                    )
+                },
+            )
+            .into_result()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fits_and_zooms_around_cursor() {
+        let viewport = [200, 100];
+        let mut view = View::fit([50, 50], viewport);
+        assert_eq!(view.center, glm::vec2(25.0, 25.0));
+        assert!((view.zoom - 1.8).abs() < 1e-6);
+
+        let middle = glm::vec2(100.0, 50.0);
+        assert_eq!(view.texture_position(middle, viewport), view.center);
+
+        let pixel = glm::vec2(30.0, 80.0);
+        let before = view.texture_position(pixel, viewport);
+        view.zoom_at(pixel, viewport, 4.0);
+        let after = view.texture_position(pixel, viewport);
+        assert!(glm::distance(&before, &after) < 1e-4);
+    }
+
+    #[test]
+    fn pans_with_the_cursor() {
+        let viewport = [100, 100];
+        let mut view = View {
+            center: glm::vec2(10.0, 10.0),
+            zoom: 4.0,
+        };
+        let pixel = glm::vec2(20.0, 20.0);
+        let before = view.texture_position(pixel, viewport);
+        view.pan(glm::vec2(8.0, -4.0));
+        let after =
+            view.texture_position(pixel + glm::vec2(8.0, -4.0), viewport);
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn channels_cycle() {
+        let mut channels = Channels::Rgb;
+        for _ in 0..5 {
+            channels = channels.next();
+        }
+        assert_eq!(channels, Channels::Rgb);
+        assert_eq!(Channels::Alpha.index(), 3);
+    }
+
+    #[test]
+    fn readout_fits_any_value() {
+        let values = [
+            [f32::MIN, -f32::MIN_POSITIVE, f32::NAN, f32::INFINITY],
+            [0.0, 1.0, -0.5, 12345.678],
+        ];
+        for &value in &values {
+            let lines = readout_lines([4095, 4095], value);
+            assert_eq!(lines.len(), READOUT_LINES);
+            for line in &lines {
+                assert!(line.len() <= READOUT_COLUMNS, "{:?}", line);
+                assert!(line.chars().all(|c| GLYPHS.contains(c)), "{:?}", line);
+            }
+        }
+        assert_eq!(readout_lines([1, 2], [0.5; 4])[1], "r 5.0000e-1");
+    }
+
+    #[test]
+    fn readout_glyphs_by_line() {
+        let glyphs = readout_glyphs(&["1".to_string(), "a".to_string()]);
+        assert_eq!(glyphs.len(), READOUT_COLUMNS * READOUT_LINES);
+        assert_eq!(glyphs[0], 2.0);
+        assert_eq!(glyphs[READOUT_COLUMNS], GLYPHS.find('a').unwrap() as f32);
+        assert_eq!(glyphs.iter().filter(|&&glyph| glyph != 0.0).count(), 2);
+    }
+}
@@ -1,22 +1,36 @@
 use crate::context::Context;
+use crate::debug::{
+    capture::{label_framebuffer, label_texture},
+    inspector::Inspectable,
+    DebugGroup,
+};
 use luminance::texture::Sampler;
 use luminance_derive::UniformInterface;
 use luminance_front::{
     context::GraphicsContext,
     framebuffer::Framebuffer,
-    pipeline::{PipelineGate, TextureBinding},
-    pixel::{Floating, RG32F, RGBA32F},
+    pipeline::{
+        BoundTexture, Pipeline, PipelineError, PipelineGate, TextureBinding,
+    },
+    pixel::{Floating, RGBA16F, RGBA32F},
     shader::{Program, Uniform},
+    shading_gate::ShadingGate,
     tess::{Mode, Tess},
     texture::{Dim2, GenMipmaps, Texture},
 };
 use std::f32::consts::TAU;
 
+mod compute;
+pub mod filter;
+
 const QUAD_VS_SRC: crate::shader::ShaderSource =
     crate::shader_source!("./shaders/quad.vert");
 
 type TwiddleTexture = Texture<Dim2, RGBA32F>;
 
+/// Profiler scope names of the transforms along rows and along columns.
+const AXES: [&str; 2] = ["fft rows", "fft columns"];
+
 fn twiddle_indices(
     context: &mut Context,
     size: u32,
@@ -74,6 +88,7 @@ fn twiddle_indices(
     }
 
     texture.upload(GenMipmaps::No, &pixels)?;
+    label_texture(context, &mut texture, &format!("twiddle indices {}", size))?;
 
     Ok(texture)
 }
@@ -84,36 +99,264 @@ struct ButterflyInterface {
     input_texture: Uniform<TextureBinding<Dim2, Floating>>,
     stage: Uniform<i32>,
     direction: Uniform<i32>,
+    exponent_sign: Uniform<f32>,
+    scale: Uniform<f32>,
 }
 
 #[derive(UniformInterface)]
 struct InversionInterface {
     input_texture: Uniform<TextureBinding<Dim2, Floating>>,
-    n: Uniform<u32>,
+    scale: Uniform<f32>,
+    checkerboard: Uniform<i32>,
+    shift: Uniform<i32>,
+}
+
+/// Two complex signals, in rg and ba, which are transformed together.
+///
+/// Signals whose transforms are real can go two to a complex one: if `a` and
+/// `b` have real transforms, the transform of `a + ib` is that of `a` plus
+/// `i` times that of `b`. So a texture carries up to four real maps.
+pub type FftTexture = Texture<Dim2, RGBA32F>;
+pub type FftFramebuffer = Framebuffer<Dim2, RGBA32F, ()>;
+
+/// Which implementation `Fft::render` runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FftBackend {
+    /// A fullscreen pass per butterfly stage along each axis, and one to
+    /// finish. Works everywhere.
+    Fragment,
+    /// A compute dispatch along each axis. Needs GL 4.3.
+    Compute,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FftDirection {
+    /// From a signal to its spectrum, with `exp(-i…)` in the sum.
+    Forward,
+    /// From a spectrum back to the signal, with `exp(+i…)` in the sum.
+    Inverse,
+}
+
+impl FftDirection {
+    fn exponent_sign(self) -> f32 {
+        match self {
+            Self::Forward => -1.0,
+            Self::Inverse => 1.0,
+        }
+    }
+}
+
+/// Where the factor of 1 / (number of texels) of a round trip is applied.
+/// Everything transformed so far uses `Backward`, so the other conventions
+/// are only built for the tests of the scaling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    /// On the inverse transform, as in the usual definition of the DFT.
+    Backward,
+    /// The square root of it on both, which preserves energy.
+    #[cfg(test)]
+    Ortho,
+    /// On the forward transform, which makes spectra hold averages.
+    #[cfg(test)]
+    Forward,
+    /// Nowhere.
+    #[cfg(test)]
+    None,
+}
+
+impl Normalization {
+    /// Factor applied to the result of a transform of `texels` values.
+    pub fn scale(self, direction: FftDirection, texels: u32) -> f32 {
+        let texels = texels as f32;
+        match (self, direction) {
+            (Self::Backward, FftDirection::Inverse) => 1.0 / texels,
+            #[cfg(test)]
+            (Self::Forward, FftDirection::Forward) => 1.0 / texels,
+            #[cfg(test)]
+            (Self::Ortho, _) => 1.0 / texels.sqrt(),
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FftOptions {
+    pub direction: FftDirection,
+    pub normalization: Normalization,
+    /// Whether spectra have the zero frequency in the middle of the texture
+    /// rather than in the corner. That is the output of a forward transform
+    /// or the input of an inverse one.
+    pub centered: bool,
+}
+
+impl FftOptions {
+    /// What `Fft::render` does, as the ocean needs it.
+    pub const OCEAN: Self = Self {
+        direction: FftDirection::Inverse,
+        normalization: Normalization::Backward,
+        centered: true,
+    };
+
+    /// Checkerboard sign flip of the output, which moves the zero frequency
+    /// of the input spectrum from the corner to the middle.
+    fn checkerboard(&self) -> bool {
+        self.centered && self.direction == FftDirection::Inverse
+    }
+
+    /// Shift of the output by half its size, which moves the zero frequency
+    /// of the output spectrum from the corner to the middle.
+    fn shift(&self) -> bool {
+        self.centered && self.direction == FftDirection::Forward
+    }
+}
+
+/// What the intermediate results of the fragment backend are stored as. The
+/// input and output are always full precision, and the compute backend keeps
+/// intermediate results in shared memory at full precision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FftPrecision {
+    Full,
+    /// Half the memory traffic, for GPUs where that is the bottleneck.
+    Half,
+}
+
+impl FftPrecision {
+    /// Factor applied by each butterfly pass. Unscaled, the sums of an
+    /// N x N transform grow up to N² times larger than the result and
+    /// overflow half floats, so at half precision every pass halves them and
+    /// the inversion pass makes up for what is left of the normalisation.
+    fn pass_scale(self) -> f32 {
+        match self {
+            Self::Full => 1.0,
+            Self::Half => 0.5,
+        }
+    }
+}
+
+/// Factors applied by each butterfly pass of the fragment backend and by its
+/// inversion pass, for a transform of `size`. They multiply to the
+/// normalisation of `options`.
+fn fragment_scales(
+    precision: FftPrecision,
+    options: &FftOptions,
+    size: [u32; 2],
+) -> (f32, f32) {
+    let [width, height] = size;
+    let passes = width.trailing_zeros() + height.trailing_zeros();
+    let pass_scale = precision.pass_scale();
+    let normalization = options
+        .normalization
+        .scale(options.direction, width * height);
+    (pass_scale, normalization / pass_scale.powi(passes as i32))
+}
+
+/// How far one transform is from a reference, relative to the largest
+/// magnitude in the reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FftError {
+    pub max: f32,
+    pub rms: f32,
 }
 
-pub type FftTexture = Texture<Dim2, RG32F>;
-pub type FftFramebuffer = Framebuffer<Dim2, RG32F, ()>;
+impl FftError {
+    fn between(reference: &[f32], other: &[f32]) -> Self {
+        let magnitude = reference.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+        let magnitude = magnitude.max(f32::MIN_POSITIVE);
+
+        let (mut max, mut sum) = (0.0f32, 0.0f64);
+        for (a, b) in reference.iter().zip(other) {
+            let difference = match (a - b).abs() / magnitude {
+                // Overflow, which max and powi would let through as NaN
+                d if d.is_nan() => f32::INFINITY,
+                d => d,
+            };
+            max = max.max(difference);
+            sum += (difference as f64).powi(2);
+        }
+        let rms = (sum / reference.len().max(1) as f64).sqrt() as f32;
+
+        Self { max, rms }
+    }
+}
+
+impl std::fmt::Display for FftError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "max {:.1e}, rms {:.1e}", self.max, self.rms)
+    }
+}
+
+type HalfFftFramebuffer = Framebuffer<Dim2, RGBA16F, ()>;
+
+/// A buffer of the fragment backend, which passes render into and read from.
+enum PassBuffer<'a> {
+    Full(&'a mut FftFramebuffer),
+    Half(&'a mut HalfFftFramebuffer),
+}
+
+impl PassBuffer<'_> {
+    fn pipeline<F>(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        f: F,
+    ) -> anyhow::Result<()>
+    where
+        F: for<'a> FnOnce(Pipeline<'a>, ShadingGate<'a>) -> anyhow::Result<()>,
+    {
+        match self {
+            Self::Full(framebuffer) => pipeline_gate
+                .pipeline(&**framebuffer, &Default::default(), f)
+                .into_result(),
+            Self::Half(framebuffer) => pipeline_gate
+                .pipeline(&**framebuffer, &Default::default(), f)
+                .into_result(),
+        }
+    }
+
+    fn bind<'a>(
+        &'a mut self,
+        pipeline: &'a Pipeline<'a>,
+    ) -> Result<BoundTexture<'a, Dim2, Floating>, PipelineError> {
+        match self {
+            Self::Full(framebuffer) => {
+                pipeline.bind_texture(framebuffer.color_slot())
+            }
+            Self::Half(framebuffer) => {
+                pipeline.bind_texture(framebuffer.color_slot())
+            }
+        }
+    }
+}
+
+fn stage_labels(length: u32, axis: &str) -> Vec<String> {
+    (1..=length.trailing_zeros())
+        .map(|stage| format!("butterfly {} of {}", stage, axis))
+        .collect()
+}
 
 pub struct Fft {
-    width: u32,
-    twiddle_indices: TwiddleTexture,
+    size: [u32; 2],
+    backend: FftBackend,
+    precision: FftPrecision,
+    compute: Option<compute::ComputeFft>,
+    /// For transforms along rows, then along columns
+    twiddle_indices: [TwiddleTexture; 2],
     butterfly_shader: Program<(), (), ButterflyInterface>,
     inversion_shader: Program<(), (), InversionInterface>,
     ping_buffer: FftFramebuffer,
+    /// Only allocated at half precision.
+    half_buffers: Option<[HalfFftFramebuffer; 2]>,
+    /// Debug group names of the butterfly passes along rows, then along
+    /// columns, made once rather than every frame.
+    stage_labels: [Vec<String>; 2],
     tess: Tess<()>,
 }
 
 impl Fft {
     pub fn framebuffer(
         context: &mut Context,
-        width: u32,
+        size: [u32; 2],
     ) -> anyhow::Result<FftFramebuffer> {
-        Ok(context.new_framebuffer(
-            [width, width],
-            0,
-            Self::default_sampler(),
-        )?)
+        Ok(context.new_framebuffer(size, 0, Self::default_sampler())?)
     }
 
     fn default_sampler() -> Sampler {
@@ -128,8 +371,76 @@ impl Fft {
         }
     }
 
-    pub fn new(context: &mut Context, width: u32) -> anyhow::Result<Self> {
-        let twiddle_indices = twiddle_indices(context, width)?;
+    /// Transforms of `width` × `height` textures, both powers of two. Uses
+    /// the compute backend where the driver supports it, unless `ARCHI_FFT`
+    /// is `fragment`, and half precision buffers if `ARCHI_FFT_PRECISION` is
+    /// `half`. Only the fragment backend has half precision, so it is chosen
+    /// then unless `ARCHI_FFT` asks for the compute one.
+    pub fn new(context: &mut Context, size: [u32; 2]) -> anyhow::Result<Self> {
+        let [width, height] = size;
+        anyhow::ensure!(
+            width.is_power_of_two()
+                && height.is_power_of_two()
+                && width.min(height) > 1,
+            "FFT size {}x{} is not a power of two above 1",
+            width,
+            height
+        );
+
+        let compute = if context.capabilities.compute
+            && width.max(height) <= compute::MAX_LENGTH
+        {
+            match compute::ComputeFft::new(context, size) {
+                Ok(compute) => Some(compute),
+                Err(e) => {
+                    tracing::warn!("compute FFT unavailable: {:#}", e);
+                    None
+                }
+            }
+        } else {
+            tracing::info!(
+                "compute FFT needs GL 4.3 and at most {} texels a side, using \
+                 fragment shaders",
+                compute::MAX_LENGTH
+            );
+            None
+        };
+
+        let precision = match std::env::var("ARCHI_FFT_PRECISION").as_deref() {
+            Ok("full") | Err(_) => FftPrecision::Full,
+            Ok("half") => FftPrecision::Half,
+            Ok(name) => anyhow::bail!("Unknown FFT precision {:?}", name),
+        };
+
+        let half = precision == FftPrecision::Half;
+        let backend = match std::env::var("ARCHI_FFT").as_deref() {
+            Ok("fragment") => FftBackend::Fragment,
+            Err(_) if half => FftBackend::Fragment,
+            Ok("compute") | Err(_) if compute.is_some() => FftBackend::Compute,
+            Ok("compute") => {
+                tracing::warn!(
+                    "compute FFT unavailable, using fragment shaders"
+                );
+                FftBackend::Fragment
+            }
+            Err(_) => FftBackend::Fragment,
+            Ok(name) => anyhow::bail!("Unknown FFT backend {:?}", name),
+        };
+        let precision = match backend {
+            FftBackend::Compute if half => {
+                tracing::warn!(
+                    "the compute FFT has no half precision, using full \
+                     precision"
+                );
+                FftPrecision::Full
+            }
+            _ => precision,
+        };
+
+        let twiddle_indices = [
+            twiddle_indices(context, width)?,
+            twiddle_indices(context, height)?,
+        ];
 
         let butterfly_shader = crate::shader::from_sources(
             context,
@@ -147,7 +458,17 @@ impl Fft {
             crate::shader_source!("./shaders/inversion.frag"),
         )?;
 
-        let ping_buffer = Self::framebuffer(context, width)?;
+        let mut ping_buffer = Self::framebuffer(context, size)?;
+        label_framebuffer(context, &ping_buffer, "fft ping")?;
+        label_texture(context, ping_buffer.color_slot(), "fft ping")?;
+
+        let half_buffers = match precision {
+            FftPrecision::Half => Some(Self::half_buffers(context, size)?),
+            FftPrecision::Full => None,
+        };
+
+        let stage_labels =
+            [stage_labels(width, AXES[0]), stage_labels(height, AXES[1])];
 
         let tess = context
             .new_tess()
@@ -156,101 +477,259 @@ impl Fft {
             .build()?;
 
         Ok(Self {
-            width,
+            size,
+            backend,
+            precision,
+            compute,
             twiddle_indices,
             butterfly_shader,
             inversion_shader,
             ping_buffer,
+            half_buffers,
+            stage_labels,
             tess,
         })
     }
 
+    fn half_buffers(
+        context: &mut Context,
+        size: [u32; 2],
+    ) -> anyhow::Result<[HalfFftFramebuffer; 2]> {
+        let mut buffers = [
+            context.new_framebuffer(size, 0, Self::default_sampler())?,
+            context.new_framebuffer(size, 0, Self::default_sampler())?,
+        ];
+        for (buffer, label) in buffers
+            .iter_mut()
+            .zip(&["fft half precision ping", "fft half precision pong"])
+        {
+            label_framebuffer(context, buffer, label)?;
+            label_texture(context, buffer.color_slot(), label)?;
+        }
+        Ok(buffers)
+    }
+
+    pub fn backend(&self) -> FftBackend {
+        self.backend
+    }
+
+    pub fn precision(&self) -> FftPrecision {
+        self.precision
+    }
+
+    /// Switch to full precision for good, freeing the half precision buffers.
+    pub fn use_full_precision(&mut self) {
+        self.precision = FftPrecision::Full;
+        self.half_buffers = None;
+    }
+
+    pub fn set_backend(&mut self, backend: FftBackend) -> anyhow::Result<()> {
+        if backend == FftBackend::Compute && self.compute.is_none() {
+            anyhow::bail!("the compute FFT backend is not available");
+        }
+        self.backend = backend;
+        Ok(())
+    }
+
+    /// Intermediate results, as left by the last transform.
+    pub fn inspectables(&mut self) -> Vec<Inspectable> {
+        let [rows, columns] = &mut self.twiddle_indices;
+        let mut textures: Vec<Inspectable> = vec![
+            ("fft twiddle indices, rows", rows),
+            ("fft twiddle indices, columns", columns),
+            ("fft ping", self.ping_buffer.color_slot()),
+        ];
+        if let Some([half_ping, half_pong]) = &mut self.half_buffers {
+            textures.push(("fft half precision ping", half_ping.color_slot()));
+            textures.push(("fft half precision pong", half_pong.color_slot()));
+        }
+        textures
+    }
+
+    /// Inverse transform of a centered spectrum, see [`FftOptions::OCEAN`].
     pub fn render<'o>(
         &mut self,
         pipeline_gate: &mut PipelineGate,
         freq_texture: &mut FftTexture,
         output_buffer: &'o mut FftFramebuffer,
+    ) -> anyhow::Result<&'o mut FftTexture> {
+        self.transform(
+            pipeline_gate,
+            freq_texture,
+            output_buffer,
+            &FftOptions::OCEAN,
+        )
+    }
+
+    pub fn transform<'o>(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        input: &mut FftTexture,
+        output_buffer: &'o mut FftFramebuffer,
+        options: &FftOptions,
+    ) -> anyhow::Result<&'o mut FftTexture> {
+        match (self.backend, &mut self.compute) {
+            (FftBackend::Compute, Some(compute)) => compute.render(
+                pipeline_gate,
+                input,
+                output_buffer,
+                &mut self.ping_buffer,
+                options,
+            ),
+            _ => self.render_fragment(
+                pipeline_gate,
+                input,
+                output_buffer,
+                options,
+            ),
+        }
+    }
+
+    /// How far the compute transform of `freq_texture` is from the full
+    /// precision fragment one. Both `outputs` are overwritten.
+    pub fn compare_backends(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        freq_texture: &mut FftTexture,
+        [fragment_buffer, compute_buffer]: [&mut FftFramebuffer; 2],
+    ) -> anyhow::Result<FftError> {
+        let (backend, precision) = (self.backend, self.precision);
+        self.precision = FftPrecision::Full;
+
+        self.set_backend(FftBackend::Compute)?;
+        let compute = self
+            .render(pipeline_gate, freq_texture, compute_buffer)?
+            .get_raw_texels()?;
+        self.set_backend(FftBackend::Fragment)?;
+        let fragment = self
+            .render(pipeline_gate, freq_texture, fragment_buffer)?
+            .get_raw_texels()?;
+
+        self.backend = backend;
+        self.precision = precision;
+
+        Ok(FftError::between(&fragment, &compute))
+    }
+
+    /// How far the half precision transform of `freq_texture` is from the
+    /// full precision one, both by the fragment backend. Both `outputs` are
+    /// overwritten. Needs the half precision buffers, which are only there
+    /// if `Fft::new` chose half precision.
+    pub fn compare_precisions(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        freq_texture: &mut FftTexture,
+        [full_buffer, half_buffer]: [&mut FftFramebuffer; 2],
+    ) -> anyhow::Result<FftError> {
+        anyhow::ensure!(
+            self.half_buffers.is_some(),
+            "no half precision FFT buffers to compare with"
+        );
+        let (backend, precision) = (self.backend, self.precision);
+        self.backend = FftBackend::Fragment;
+
+        self.precision = FftPrecision::Full;
+        let full = self
+            .render(pipeline_gate, freq_texture, full_buffer)?
+            .get_raw_texels()?;
+        self.precision = FftPrecision::Half;
+        let half = self
+            .render(pipeline_gate, freq_texture, half_buffer)?
+            .get_raw_texels()?;
+
+        self.backend = backend;
+        self.precision = precision;
+
+        Ok(FftError::between(&full, &half))
+    }
+
+    fn render_fragment<'o>(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        freq_texture: &mut FftTexture,
+        output_buffer: &'o mut FftFramebuffer,
+        options: &FftOptions,
     ) -> anyhow::Result<&'o mut FftTexture> {
         let Self {
-            width,
+            size: [width, height],
+            precision,
             tess,
             twiddle_indices,
             butterfly_shader,
             inversion_shader,
             ping_buffer,
+            half_buffers,
+            stage_labels,
             ..
         } = self;
 
-        let stages = 31 - u32::leading_zeros(*width);
-
-        let mut pingpong_buffers = [ping_buffer, output_buffer];
+        let passes = width.trailing_zeros() + height.trailing_zeros();
+        let (pass_scale, scale) =
+            fragment_scales(*precision, options, [*width, *height]);
+
+        // The butterfly passes go back and forth between two buffers, the
+        // last pass from the one written last into the output. At full
+        // precision the output is one of the two, so the order is picked
+        // for the last butterfly pass not to write to it.
+        let output = &mut *output_buffer;
+        let (mut buffers, last_target) = match (precision, half_buffers) {
+            (FftPrecision::Full, _) if passes % 2 == 0 => (
+                [PassBuffer::Full(ping_buffer), PassBuffer::Full(output)],
+                None,
+            ),
+            (FftPrecision::Full, _) => (
+                [PassBuffer::Full(output), PassBuffer::Full(ping_buffer)],
+                None,
+            ),
+            (FftPrecision::Half, Some([half_ping, half_pong])) => (
+                [PassBuffer::Half(half_ping), PassBuffer::Half(half_pong)],
+                Some(PassBuffer::Full(output)),
+            ),
+            (FftPrecision::Half, None) => {
+                anyhow::bail!("no half precision FFT buffers")
+            }
+        };
 
         let mut initial_texture = Some(freq_texture);
 
-        for &direction in &[0, 1] {
+        for (direction, length) in [*width, *height].iter().enumerate() {
+            let _scope = crate::profiler::scope(AXES[direction]);
+            let stages = length.trailing_zeros();
+            let twiddle_indices = &mut twiddle_indices[direction];
+            let stage_labels = &stage_labels[direction];
+            let direction = direction as i32;
             for stage in 0..stages {
-                let [in_buffer, out_buffer] = pingpong_buffers;
-
-                let texture = match initial_texture.take() {
-                    Some(t) => t,
-                    None => in_buffer.color_slot(),
-                };
-
-                pipeline_gate
-                    .pipeline(
-                        out_buffer,
-                        &Default::default(),
-                        |pipeline, mut shader_gate| -> anyhow::Result<()> {
-                            let bound_twiddle =
-                                pipeline.bind_texture(twiddle_indices)?;
-                            let bound_input = pipeline.bind_texture(texture)?;
-                            shader_gate.shade(
-                                butterfly_shader,
-                                |mut iface, uni, mut render_gate| {
-                                    iface.set(
-                                        &uni.twiddle_indices,
-                                        bound_twiddle.binding(),
-                                    );
-                                    iface.set(
-                                        &uni.input_texture,
-                                        bound_input.binding(),
-                                    );
-                                    iface.set(&uni.stage, stage as i32);
-                                    iface.set(&uni.direction, direction);
-                                    render_gate.render(
-                                        &Default::default(),
-                                        |mut tess_gate| {
-                                            tess_gate.render(&*tess)
-                                        },
-                                    )
-                                },
-                            )
-                        },
-                    )
-                    .into_result()?;
-
-                pingpong_buffers = [out_buffer, in_buffer];
-            }
-        }
-        {
-            let [in_buffer, out_buffer] = pingpong_buffers;
-
-            let texture = in_buffer.color_slot();
-
-            pipeline_gate
-                .pipeline(
-                    out_buffer,
-                    &Default::default(),
-                    |pipeline, mut shader_gate| -> anyhow::Result<()> {
-                        let bound_input = pipeline.bind_texture(texture)?;
+                let _group = DebugGroup::new(&stage_labels[stage as usize]);
+                let [in_buffer, out_buffer] = &mut buffers;
+                let texture = initial_texture.take();
+
+                out_buffer.pipeline(
+                    pipeline_gate,
+                    |pipeline, mut shader_gate| {
+                        let bound_twiddle =
+                            pipeline.bind_texture(twiddle_indices)?;
+                        let bound_input = match texture {
+                            Some(texture) => pipeline.bind_texture(texture)?,
+                            None => in_buffer.bind(&pipeline)?,
+                        };
                         shader_gate.shade(
-                            inversion_shader,
+                            butterfly_shader,
                             |mut iface, uni, mut render_gate| {
+                                iface.set(
+                                    &uni.twiddle_indices,
+                                    bound_twiddle.binding(),
+                                );
                                 iface.set(
                                     &uni.input_texture,
                                     bound_input.binding(),
                                 );
-                                iface.set(&uni.n, *width);
+                                iface.set(&uni.stage, stage as i32);
+                                iface.set(&uni.scale, pass_scale);
+                                iface.set(&uni.direction, direction);
+                                iface.set(
+                                    &uni.exponent_sign,
+                                    options.direction.exponent_sign(),
+                                );
                                 render_gate.render(
                                     &Default::default(),
                                     |mut tess_gate| tess_gate.render(&*tess),
@@ -258,10 +737,119 @@ impl Fft {
                             },
                         )
                     },
-                )
-                .into_result()?;
+                )?;
+
+                buffers.swap(0, 1);
+            }
+        }
+        {
+            let _scope = crate::profiler::scope("fft inversion");
+            let [mut in_buffer, spare] = buffers;
+            let mut out_buffer = last_target.unwrap_or(spare);
+
+            out_buffer.pipeline(
+                pipeline_gate,
+                |pipeline, mut shader_gate| {
+                    let bound_input = in_buffer.bind(&pipeline)?;
+                    shader_gate.shade(
+                        inversion_shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface
+                                .set(&uni.input_texture, bound_input.binding());
+                            iface.set(&uni.scale, scale);
+                            iface.set(
+                                &uni.checkerboard,
+                                options.checkerboard() as i32,
+                            );
+                            iface.set(&uni.shift, options.shift() as i32);
+                            render_gate
+                                .render(&Default::default(), |mut tess_gate| {
+                                    tess_gate.render(&*tess)
+                                })
+                        },
+                    )
+                },
+            )?;
         }
 
         Ok(output_buffer.color_slot())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalization_round_trip() {
+        let texels = 64;
+        for &normalization in &[
+            Normalization::Backward,
+            Normalization::Ortho,
+            Normalization::Forward,
+        ] {
+            let round_trip = normalization.scale(FftDirection::Forward, texels)
+                * normalization.scale(FftDirection::Inverse, texels);
+            assert!((round_trip * texels as f32 - 1.0).abs() < 1e-6);
+        }
+        assert_eq!(
+            Normalization::None.scale(FftDirection::Inverse, texels),
+            1.0
+        );
+    }
+
+    #[test]
+    fn fragment_scales_multiply_to_normalization() {
+        for &size in &[[512_u32, 512], [64, 16]] {
+            let [width, height] = size;
+            let passes =
+                (width.trailing_zeros() + height.trailing_zeros()) as i32;
+            for &precision in &[FftPrecision::Full, FftPrecision::Half] {
+                for options in &[
+                    FftOptions::OCEAN,
+                    FftOptions {
+                        direction: FftDirection::Forward,
+                        normalization: Normalization::Backward,
+                        centered: false,
+                    },
+                ] {
+                    let (pass, inversion) =
+                        fragment_scales(precision, options, size);
+                    let expected = options
+                        .normalization
+                        .scale(options.direction, width * height);
+                    let product = pass.powi(passes) * inversion;
+                    assert!((product / expected - 1.0).abs() < 1e-6);
+                }
+            }
+        }
+
+        // At half precision a pass at most doubles the largest value, so
+        // the passes keep sums as large as the input, and for the ocean they
+        // take up the whole normalisation
+        let (pass, inversion) =
+            fragment_scales(FftPrecision::Half, &FftOptions::OCEAN, [512, 512]);
+        assert!(2.0 * pass <= 1.0);
+        assert_eq!(inversion, 1.0);
+        let (pass, _) =
+            fragment_scales(FftPrecision::Full, &FftOptions::OCEAN, [512, 512]);
+        assert_eq!(pass, 1.0);
+    }
+
+    #[test]
+    fn error_relative_to_reference() {
+        let reference = [2.0, -4.0, 1.0, 0.0];
+        assert_eq!(
+            FftError::between(&reference, &reference),
+            FftError { max: 0.0, rms: 0.0 }
+        );
+
+        let error = FftError::between(&reference, &[2.0, -3.0, 1.0, 0.0]);
+        assert_eq!(error.max, 0.25);
+        assert!((error.rms - 0.125).abs() < 1e-6);
+
+        let overflow = [f32::INFINITY, f32::NAN, 1.0, 0.0];
+        let error = FftError::between(&reference, &overflow);
+        assert!(!error.max.is_finite());
+    }
+}
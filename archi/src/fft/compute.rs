@@ -0,0 +1,286 @@
+//! The transform as two compute dispatches, one along each axis, each work
+//! group transforming a whole row or column in shared memory.
+
+use super::{FftFramebuffer, FftOptions, FftTexture, AXES};
+use crate::context::Context;
+use crate::shader::{bound_texture_name, ComputeProgram};
+use gl::types::{GLint, GLuint};
+use luminance_front::pipeline::PipelineGate;
+
+/// Longest transform that fits the shared memory and work group size every
+/// GL 4.3 driver must offer: two copies of a line of 16 byte pixels take up
+/// all of the 32 KiB.
+pub const MAX_LENGTH: u32 = 1024;
+
+/// The program transforming lines along one axis, built for their length.
+struct Pass {
+    program: ComputeProgram,
+    direction: GLint,
+    exponent_sign: GLint,
+    scale: GLint,
+    checkerboard: GLint,
+    shift: GLint,
+}
+
+impl Pass {
+    fn new(context: &mut Context, length: u32) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            length.is_power_of_two() && (4..=MAX_LENGTH).contains(&length),
+            "compute FFT length {} is not a power of two in 4..={}",
+            length,
+            MAX_LENGTH
+        );
+
+        let program = ComputeProgram::new(
+            context,
+            &crate::shader_source!("./shaders/fft.comp"),
+            &[("N", length.to_string())],
+        )?;
+
+        Ok(Self {
+            direction: program.uniform_location("direction"),
+            exponent_sign: program.uniform_location("exponent_sign"),
+            scale: program.uniform_location("scale"),
+            checkerboard: program.uniform_location("checkerboard"),
+            shift: program.uniform_location("shift"),
+            program,
+        })
+    }
+
+    /// Transform the `lines` rows (direction 0) or columns (direction 1) of
+    /// `input` into `output`. Only the last pass normalises and flips signs.
+    unsafe fn dispatch(
+        &self,
+        direction: i32,
+        lines: u32,
+        [input, output]: [GLuint; 2],
+        options: &FftOptions,
+        scale: f32,
+    ) {
+        let last = direction == 1;
+        self.program.with(|| {
+            gl::BindImageTexture(
+                0,
+                input,
+                0,
+                gl::FALSE,
+                0,
+                gl::READ_ONLY,
+                gl::RGBA32F,
+            );
+            gl::BindImageTexture(
+                1,
+                output,
+                0,
+                gl::FALSE,
+                0,
+                gl::WRITE_ONLY,
+                gl::RGBA32F,
+            );
+            gl::Uniform1i(self.direction, direction);
+            gl::Uniform1f(
+                self.exponent_sign,
+                options.direction.exponent_sign(),
+            );
+            gl::Uniform1i(self.shift, options.shift() as _);
+            gl::Uniform1f(self.scale, if last { scale } else { 1.0 });
+            gl::Uniform1i(
+                self.checkerboard,
+                (last && options.checkerboard()) as _,
+            );
+            gl::DispatchCompute(lines, 1, 1);
+        });
+    }
+}
+
+pub struct ComputeFft {
+    size: [u32; 2],
+    /// Along rows, then along columns
+    passes: [Pass; 2],
+}
+
+impl ComputeFft {
+    pub fn new(context: &mut Context, size: [u32; 2]) -> anyhow::Result<Self> {
+        let [width, height] = size;
+        Ok(Self {
+            size,
+            passes: [Pass::new(context, width)?, Pass::new(context, height)?],
+        })
+    }
+
+    /// Same as the fragment path, with `scratch` only used to get hold of a
+    /// pipeline in which to find the texture names.
+    pub fn render<'o>(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        freq_texture: &mut FftTexture,
+        output_buffer: &'o mut FftFramebuffer,
+        scratch: &mut FftFramebuffer,
+        options: &FftOptions,
+    ) -> anyhow::Result<&'o mut FftTexture> {
+        let Self {
+            size: [width, height],
+            passes: [rows, columns],
+        } = self;
+
+        let output = output_buffer.color_slot();
+        let scale = options
+            .normalization
+            .scale(options.direction, *width * *height);
+
+        pipeline_gate
+            .pipeline(
+                scratch,
+                &Default::default(),
+                |pipeline, _| -> anyhow::Result<()> {
+                    let input = pipeline.bind_texture(freq_texture)?;
+                    let input = bound_texture_name(input.binding().binding());
+                    let output = pipeline.bind_texture(output)?;
+                    let output = bound_texture_name(output.binding().binding());
+
+                    unsafe {
+                        // Rows, from the input into the output
+                        let scope = crate::profiler::scope(AXES[0]);
+                        rows.dispatch(
+                            0,
+                            *height,
+                            [input, output],
+                            options,
+                            scale,
+                        );
+                        gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+                        drop(scope);
+
+                        // Columns, in place. Each work group reads its whole
+                        // column before writing any of it.
+                        let _scope = crate::profiler::scope(AXES[1]);
+                        columns.dispatch(
+                            1,
+                            *width,
+                            [output, output],
+                            options,
+                            scale,
+                        );
+                        gl::MemoryBarrier(
+                            gl::TEXTURE_FETCH_BARRIER_BIT
+                                | gl::FRAMEBUFFER_BARRIER_BIT
+                                | gl::SHADER_IMAGE_ACCESS_BARRIER_BIT,
+                        );
+                    }
+
+                    Ok(())
+                },
+            )
+            .into_result()?;
+
+        Ok(output_buffer.color_slot())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::TAU;
+
+    type Complex = (f64, f64);
+
+    fn mul((a, b): Complex, (c, d): Complex) -> Complex {
+        (a * c - b * d, a * d + b * c)
+    }
+
+    fn add((a, b): Complex, (c, d): Complex) -> Complex {
+        (a + c, b + d)
+    }
+
+    fn sub((a, b): Complex, (c, d): Complex) -> Complex {
+        (a - c, b - d)
+    }
+
+    fn twiddle(sign: f64, r: usize, k: usize, span: usize) -> Complex {
+        let angle = sign * TAU * (r * k) as f64 / span as f64;
+        (angle.cos(), angle.sin())
+    }
+
+    // The stages of fft.comp, one thread after the other, copied by hand.
+    // Only reading the two side by side ties this to the shader
+    fn stockham(input: &[Complex], sign: f64) -> Vec<Complex> {
+        let n = input.len();
+        let quarter = n / 4;
+        let mut src = input.to_vec();
+        let mut dst = vec![(0.0, 0.0); n];
+
+        let mut ns = 1;
+        while ns * 4 <= n {
+            for j in 0..quarter {
+                let v: Vec<_> = (0..4)
+                    .map(|r| {
+                        mul(
+                            src[j + r * quarter],
+                            twiddle(sign, r, j % ns, ns * 4),
+                        )
+                    })
+                    .collect();
+
+                let a0 = add(v[0], v[2]);
+                let a1 = sub(v[0], v[2]);
+                let a2 = add(v[1], v[3]);
+                let a3 = (sign * (v[3].1 - v[1].1), sign * (v[1].0 - v[3].0));
+
+                let d = (j / ns) * ns * 4 + j % ns;
+                dst[d] = add(a0, a2);
+                dst[d + ns] = add(a1, a3);
+                dst[d + 2 * ns] = sub(a0, a2);
+                dst[d + 3 * ns] = sub(a1, a3);
+            }
+            std::mem::swap(&mut src, &mut dst);
+            ns *= 4;
+        }
+        if ns < n {
+            for j in 0..n / 2 {
+                let a = src[j];
+                let b = mul(src[j + n / 2], twiddle(sign, 1, j % ns, ns * 2));
+
+                let d = (j / ns) * ns * 2 + j % ns;
+                dst[d] = add(a, b);
+                dst[d + ns] = sub(a, b);
+            }
+            std::mem::swap(&mut src, &mut dst);
+        }
+
+        src
+    }
+
+    fn dft(input: &[Complex], sign: f64) -> Vec<Complex> {
+        let n = input.len();
+        (0..n)
+            .map(|k| {
+                input.iter().enumerate().fold((0.0, 0.0), |sum, (i, &x)| {
+                    let angle = sign * TAU * (i * k % n) as f64 / n as f64;
+                    add(sum, mul(x, (angle.cos(), angle.sin())))
+                })
+            })
+            .collect()
+    }
+
+    // Covers the index arithmetic of the Rust mirror above, not fft.comp
+    // itself, which needs a GL context. The shader is checked against the
+    // fragment backend at startup instead, by `Fft::compare_backends` in
+    // `Ocean::new`.
+    #[test]
+    fn matches_dft() {
+        // Both even and odd numbers of stages, to cover the radix-2 step
+        for &n in &[4, 8, 16, 32, 64, 512] {
+            let input: Vec<Complex> = (0..n)
+                .map(|i| ((i as f64 * 0.37).sin(), (i as f64 * 1.3).cos()))
+                .collect();
+
+            for &sign in &[1.0, -1.0] {
+                let expected = dft(&input, sign);
+                let actual = stockham(&input, sign);
+                for (e, a) in expected.iter().zip(&actual) {
+                    let error = (e.0 - a.0).hypot(e.1 - a.1);
+                    assert!(error < 1e-9 * n as f64, "n = {}: {}", n, error);
+                }
+            }
+        }
+    }
+}
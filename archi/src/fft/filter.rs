@@ -0,0 +1,331 @@
+//! Convolution by way of the frequency domain, which costs the same whatever
+//! the size of the kernel.
+
+use super::{
+    Fft, FftDirection, FftError, FftFramebuffer, FftOptions, FftTexture,
+    Normalization,
+};
+use crate::context::Context;
+use luminance_derive::UniformInterface;
+use luminance_front::{
+    context::GraphicsContext,
+    pipeline::{PipelineGate, TextureBinding},
+    pixel::Floating,
+    shader::{Program, Uniform},
+    tess::{Mode, Tess},
+    texture::{Dim2, GenMipmaps},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::f32::consts::PI;
+
+/// Seed of the noise blurred by `compare_gaussian`.
+const SEED: u64 = 0xb105;
+
+#[derive(UniformInterface)]
+struct MultiplyInterface {
+    spectrum_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    kernel_texture: Uniform<TextureBinding<Dim2, Floating>>,
+}
+
+/// Frequency of texel `i` of a spectrum of `length`, in cycles per texel,
+/// with the upper half negative.
+fn frequency(i: u32, length: u32) -> f32 {
+    let (i, length) = (i as i64, length as i64);
+    (if i < length / 2 { i } else { i - length }) as f32 / length as f32
+}
+
+/// Spectrum of a Gaussian of standard deviation `sigma` texels, wrapping
+/// around the edges of a texture of `size`, for both signals.
+pub fn gaussian_spectrum(
+    size: [u32; 2],
+    sigma: f32,
+) -> Vec<(f32, f32, f32, f32)> {
+    let [width, height] = size;
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let (fx, fy) = (frequency(x, width), frequency(y, height));
+            let value =
+                (-2.0 * PI * PI * sigma * sigma * (fx * fx + fy * fy)).exp();
+            pixels.push((value, 0.0, value, 0.0));
+        }
+    }
+    pixels
+}
+
+/// The kernel whose spectrum is `gaussian_spectrum`, along one axis of
+/// `length` texels. That spectrum is separable, so the kernel of a texture is
+/// the product of one along each axis.
+fn gaussian_kernel(length: u32, sigma: f32) -> Vec<f32> {
+    // Inverse DFT of a real and even spectrum
+    (0..length)
+        .map(|x| {
+            let sum: f32 = (0..length)
+                .map(|k| {
+                    let f = frequency(k, length);
+                    let value = (-2.0 * PI * PI * sigma * sigma * f * f).exp();
+                    let angle = 2.0 * PI * (k * x % length) as f32;
+                    value * (angle / length as f32).cos()
+                })
+                .sum();
+            sum / length as f32
+        })
+        .collect()
+}
+
+/// RGBA `pixels` of a texture of `size` blurred on the CPU by the Gaussian of
+/// `gaussian_spectrum`, wrapping around the edges.
+fn gaussian_blur(size: [u32; 2], sigma: f32, pixels: &[f32]) -> Vec<f32> {
+    let [width, height] = [size[0] as usize, size[1] as usize];
+    let texel = |x: usize, y: usize| (y * width + x) * 4;
+
+    let kernel = gaussian_kernel(size[0], sigma);
+    let mut rows = vec![0.0; pixels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            for i in 0..width {
+                let weight = kernel[(x + width - i) % width];
+                for c in 0..4 {
+                    rows[texel(x, y) + c] += weight * pixels[texel(i, y) + c];
+                }
+            }
+        }
+    }
+
+    let kernel = gaussian_kernel(size[1], sigma);
+    let mut blurred = vec![0.0; pixels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            for i in 0..height {
+                let weight = kernel[(y + height - i) % height];
+                for c in 0..4 {
+                    blurred[texel(x, y) + c] += weight * rows[texel(x, i) + c];
+                }
+            }
+        }
+    }
+    blurred
+}
+
+/// How far blurring noise of `size` by `sigma` with a `SpectralFilter` is
+/// from the same blur on the CPU.
+pub fn compare_gaussian(
+    context: &mut Context,
+    size: [u32; 2],
+    sigma: f32,
+) -> anyhow::Result<FftError> {
+    let [width, height] = size;
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let pixels: Vec<(f32, f32, f32, f32)> = (0..width * height)
+        .map(|_| rng.gen::<(f32, f32, f32, f32)>())
+        .collect();
+
+    let mut input: FftTexture =
+        context.new_texture(size, 0, Fft::default_sampler())?;
+    input.upload(GenMipmaps::No, &pixels)?;
+    let reference = gaussian_blur(size, sigma, &input.get_raw_texels()?);
+
+    let mut output = Fft::framebuffer(context, size)?;
+    let mut fft = Fft::new(context, size)?;
+    fft.use_full_precision();
+    let mut filter = SpectralFilter::gaussian(context, size, sigma)?;
+
+    let blurred = filter
+        .render(
+            &mut context.new_pipeline_gate(),
+            &mut fft,
+            &mut input,
+            &mut output,
+        )?
+        .get_raw_texels()?;
+
+    Ok(FftError::between(&reference, &blurred))
+}
+
+/// Forward FFT, multiplication by the spectrum of a kernel, inverse FFT: a
+/// convolution of the input with the kernel, wrapping around the edges.
+pub struct SpectralFilter {
+    kernel: FftTexture,
+    spectrum: FftFramebuffer,
+    product: FftFramebuffer,
+    shader: Program<(), (), MultiplyInterface>,
+    tess: Tess<()>,
+}
+
+impl SpectralFilter {
+    /// `kernel` is the spectrum to multiply by, of the same `size` as the
+    /// input, with the zero frequency in the corner as an unnormalised
+    /// forward transform gives it. The rg and ba signals each have their own.
+    pub fn new(
+        context: &mut Context,
+        size: [u32; 2],
+        kernel: &[(f32, f32, f32, f32)],
+    ) -> anyhow::Result<Self> {
+        let [width, height] = size;
+        use luminance_front::texture::{MagFilter, MinFilter, Sampler};
+
+        anyhow::ensure!(
+            kernel.len() == (width * height) as usize,
+            "kernel of {} texels for a {}x{} filter",
+            kernel.len(),
+            width,
+            height
+        );
+
+        let sampler = Sampler {
+            mag_filter: MagFilter::Nearest,
+            min_filter: MinFilter::Nearest,
+            ..Default::default()
+        };
+        let mut texture = context.new_texture(size, 0, sampler)?;
+        texture.upload(GenMipmaps::No, kernel)?;
+
+        let shader = crate::shader::from_sources(
+            context,
+            None,
+            super::QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/spectral_multiply.frag"),
+        )?;
+
+        let tess = context
+            .new_tess()
+            .set_mode(Mode::TriangleStrip)
+            .set_vertex_nb(4)
+            .build()?;
+
+        Ok(Self {
+            kernel: texture,
+            spectrum: Fft::framebuffer(context, size)?,
+            product: Fft::framebuffer(context, size)?,
+            shader,
+            tess,
+        })
+    }
+
+    /// A blur by a Gaussian of standard deviation `sigma` texels.
+    pub fn gaussian(
+        context: &mut Context,
+        size: [u32; 2],
+        sigma: f32,
+    ) -> anyhow::Result<Self> {
+        Self::new(context, size, &gaussian_spectrum(size, sigma))
+    }
+
+    pub fn render<'o>(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        fft: &mut Fft,
+        input: &mut FftTexture,
+        output_buffer: &'o mut FftFramebuffer,
+    ) -> anyhow::Result<&'o mut FftTexture> {
+        let Self {
+            kernel,
+            spectrum,
+            product,
+            shader,
+            tess,
+        } = self;
+
+        let forward = FftOptions {
+            direction: FftDirection::Forward,
+            normalization: Normalization::Backward,
+            centered: false,
+        };
+        let spectrum =
+            fft.transform(pipeline_gate, input, spectrum, &forward)?;
+
+        pipeline_gate
+            .pipeline(
+                &*product,
+                &Default::default(),
+                |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                    let spectrum = pipeline.bind_texture(spectrum)?;
+                    let kernel = pipeline.bind_texture(kernel)?;
+                    shader_gate.shade(
+                        shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface
+                                .set(&uni.spectrum_texture, spectrum.binding());
+                            iface.set(&uni.kernel_texture, kernel.binding());
+                            render_gate
+                                .render(&Default::default(), |mut tess_gate| {
+                                    tess_gate.render(&*tess)
+                                })
+                        },
+                    )
+                },
+            )
+            .into_result()?;
+
+        let inverse = FftOptions {
+            direction: FftDirection::Inverse,
+            ..forward
+        };
+        fft.transform(
+            pipeline_gate,
+            product.color_slot(),
+            output_buffer,
+            &inverse,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gaussian_spectrum_is_symmetric_low_pass() {
+        let [width, height] = [16, 8];
+        let spectrum = gaussian_spectrum([width, height], 2.0);
+        let at = |x: u32, y: u32| spectrum[(y * width + x) as usize].0;
+
+        // Keeps the mean
+        assert_eq!(spectrum[0], (1.0, 0.0, 1.0, 0.0));
+        for y in 0..height {
+            for x in 0..width {
+                // Real kernel, so the spectrum is even
+                let mirror = at((width - x) % width, (height - y) % height);
+                assert_eq!(at(x, y), mirror);
+            }
+        }
+        // Falls off towards the highest frequency, in the middle
+        for x in 1..=width / 2 {
+            assert!(at(x, 0) < at(x - 1, 0));
+        }
+        for y in 1..=height / 2 {
+            assert!(at(0, y) < at(0, y - 1));
+        }
+        // The same frequency in cycles per texel along either axis
+        assert_eq!(at(4, 0), at(0, 2));
+    }
+
+    #[test]
+    fn gaussian_blur_spreads_an_impulse_by_the_kernel() {
+        let size = [16, 8];
+        let kernel = [gaussian_kernel(16, 1.5), gaussian_kernel(8, 1.5)];
+        for kernel in &kernel {
+            let sum: f32 = kernel.iter().sum();
+            assert!((sum - 1.0).abs() < 1e-5);
+            assert!(
+                kernel[1] < kernel[0]
+                    && (kernel[1] - kernel[kernel.len() - 1]).abs() < 1e-6
+            );
+        }
+
+        let mut impulse = vec![0.0; 16 * 8 * 4];
+        impulse[(2 * 16 + 3) * 4 + 1] = 1.0;
+        let blurred = gaussian_blur(size, 1.5, &impulse);
+        for y in 0..8 {
+            for x in 0..16 {
+                let expected =
+                    kernel[0][(x + 16 - 3) % 16] * kernel[1][(y + 8 - 2) % 8];
+                let texel = (y * 16 + x) * 4;
+                assert!((blurred[texel + 1] - expected).abs() < 1e-6);
+                assert_eq!(blurred[texel], 0.0);
+            }
+        }
+    }
+}
@@ -2,25 +2,78 @@ use crate::context::Context;
 use luminance_front::context::GraphicsContext;
 use luminance_front::tess::{Mode, Tess};
 
+/// Indices of a `side_length` × `side_length` grid of quad patches. The
+/// patches are ordered chunk by chunk, so that each `chunk_size` ×
+/// `chunk_size` block of them is a contiguous range of indices, which can be
+/// drawn on its own. Chunks, and patches within them, go along y first.
+fn patch_indices(side_length: u32, chunk_size: u32) -> Vec<u32> {
+    assert_eq!(
+        side_length % chunk_size,
+        0,
+        "chunks must evenly divide the grid"
+    );
+
+    let capacity = {
+        let side_length = side_length as usize;
+        side_length * side_length * 4
+    };
+
+    let mut indices = Vec::with_capacity(capacity);
+
+    let line_count = side_length + 1;
+    for chunk_x in (0..side_length).step_by(chunk_size as usize) {
+        for chunk_y in (0..side_length).step_by(chunk_size as usize) {
+            for x in chunk_x..chunk_x + chunk_size {
+                for y in chunk_y..chunk_y + chunk_size {
+                    indices.push(x * line_count + y);
+                    indices.push(x * line_count + y + 1);
+                    indices.push(x * line_count + y + line_count + 1);
+                    indices.push(x * line_count + y + line_count);
+                }
+            }
+        }
+    }
+
+    assert_eq!(indices.len(), capacity);
+    indices
+}
+
+/// A grid of quad patches over `side_length + 1` squared vertices, see
+/// [`patch_indices`] for their order. The vertices have no attributes, their
+/// position is derived from `gl_VertexID`.
 pub fn square_patch_grid(
     context: &mut Context,
     side_length: u32,
+    chunk_size: u32,
 ) -> anyhow::Result<Tess<(), u32>> {
-    let indices = {
-        let capacity = {
-            let side_length = side_length as usize;
-            side_length * side_length * 4
-        };
+    let indices = patch_indices(side_length, chunk_size);
 
+    let tess = context
+        .new_tess()
+        .set_mode(Mode::Patch(4))
+        .set_vertex_nb(indices.len())
+        .set_indices(indices)
+        .build()?;
+
+    Ok(tess)
+}
+
+/// Triangles joining a `width` × `height` grid of vertices, indexed row by
+/// row. The vertices have no attributes, their position is derived from
+/// `gl_VertexID`.
+pub fn triangle_grid(
+    context: &mut Context,
+    [width, height]: [u32; 2],
+) -> anyhow::Result<Tess<(), u32>> {
+    let indices = {
+        let capacity = ((width - 1) * (height - 1) * 6) as usize;
         let mut indices = Vec::with_capacity(capacity);
 
-        for x in 0..side_length {
-            for y in 0..side_length {
-                let line_count = side_length + 1;
-                indices.push(x * line_count + y);
-                indices.push(x * line_count + y + 1);
-                indices.push(x * line_count + y + line_count + 1);
-                indices.push(x * line_count + y + line_count);
+        for y in 0..height - 1 {
+            for x in 0..width - 1 {
+                let i = y * width + x;
+                indices.extend_from_slice(&[i, i + 1, i + width]);
+                indices.extend_from_slice(&[i + 1, i + width + 1, i + width]);
             }
         }
 
@@ -30,10 +83,48 @@ pub fn square_patch_grid(
 
     let tess = context
         .new_tess()
-        .set_mode(Mode::Patch(4))
+        .set_mode(Mode::Triangle)
         .set_vertex_nb(indices.len())
         .set_indices(indices)
         .build()?;
 
     Ok(tess)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chunks_are_contiguous() {
+        let (side_length, chunk_size) = (8, 4);
+        let indices = patch_indices(side_length, chunk_size);
+        let line_count = side_length + 1;
+
+        let chunk_len = (chunk_size * chunk_size * 4) as usize;
+        for (chunk, indices) in indices.chunks(chunk_len).enumerate() {
+            let chunk = chunk as u32;
+            let (chunk_x, chunk_y) = (
+                chunk / (side_length / chunk_size) * chunk_size,
+                chunk % (side_length / chunk_size) * chunk_size,
+            );
+            for &i in indices {
+                let (x, y) = (i / line_count, i % line_count);
+                assert!((chunk_x..=chunk_x + chunk_size).contains(&x));
+                assert!((chunk_y..=chunk_y + chunk_size).contains(&y));
+            }
+        }
+    }
+
+    #[test]
+    fn every_patch_once() {
+        let side_length = 8;
+        let mut patches: Vec<_> = patch_indices(side_length, 2)
+            .chunks(4)
+            .map(|patch| patch[0])
+            .collect();
+        patches.sort_unstable();
+        patches.dedup();
+        assert_eq!(patches.len(), (side_length * side_length) as usize);
+    }
+}
@@ -10,6 +10,7 @@ use luminance_front::context::GraphicsContext;
 
 mod shader;
 
+mod bench;
 mod camera;
 mod context;
 mod debug;
@@ -18,25 +19,61 @@ mod grid;
 mod input;
 mod noise;
 mod ocean;
+mod postprocess;
+mod profiler;
 mod skybox;
 
 fn start_loading() -> std::time::Instant {
-    eprintln!("loading...");
+    tracing::info!("loading...");
     std::time::Instant::now()
 }
 
 fn finish_loading(start: std::time::Instant) {
     let loading_duration = start.elapsed();
-    eprintln!("finished loading in {:.2}s", loading_duration.as_secs_f32());
+    tracing::info!(
+        "finished loading in {:.2}s",
+        loading_duration.as_secs_f32()
+    );
+}
+
+/// Log to stderr, filtered by `RUST_LOG` or at info and above.
+fn init_logging() {
+    use tracing_subscriber::EnvFilter;
+
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
 }
 
 fn main() -> anyhow::Result<()> {
+    init_logging();
+
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("bench") => return bench::run(bench::Options::parse(args)?),
+        Some(command) => anyhow::bail!("Unknown command {:?}", command),
+        None => {}
+    }
+
     let loading_start = start_loading();
 
     let event_loop = EventLoop::new();
     let (mut context, mut surface) = context::Surface::new(&event_loop);
 
-    debug::glerr::debug_messages(debug::glerr::GlDebugSeverity::Low);
+    let gl_messages = debug::glerr::DebugMessages::from_env()?;
+
+    if std::env::var_os("ARCHI_PROFILE").is_some() {
+        profiler::set_enabled(true)?;
+    }
+
+    // Blur noise with the FFT and on the CPU, to see that they agree
+    if std::env::var_os("ARCHI_CHECK_SPECTRAL_FILTER").is_some() {
+        let error = fft::filter::compare_gaussian(&mut context, [64, 32], 2.0)?;
+        tracing::info!(%error, "spectral Gaussian blur against the CPU one");
+    }
 
     let [width, height] = surface.size();
 
@@ -45,22 +82,33 @@ fn main() -> anyhow::Result<()> {
 
     surface.ctx.window().set_visible(true);
 
-    let mut state = AppState {
-        debugger: debug::Debugger::new(&mut context)?,
-        blue_noise: noise::BlueNoise::new(&mut context)?,
-        back_buffer: context.back_buffer(surface.size())?,
-        camera: camera::Camera::new(width, height),
-        input: input::Input::default(),
-        skybox: skybox::Skybox::new(&mut context)?,
-        ocean: ocean::Ocean::new(&mut context)?,
-        exposure: 0.2,
-        render_water: true,
-    };
+    let mut back_buffer = context.back_buffer([width, height])?;
+    let skybox = skybox::Skybox::new(&mut context)?;
+    let mut state = AppState::new(
+        &mut context,
+        [width, height],
+        skybox,
+        rand::random(),
+        gl_messages,
+    )?;
+
+    // Poses of the camera every frame, as a path for `archi bench`
+    let mut camera_recording = std::env::var_os("ARCHI_RECORD_CAMERA")
+        .map(std::fs::File::create)
+        .transpose()
+        .context("Failed to create camera recording")?;
 
     let mut on_event = move |event: Event<()>,
                              control_flow: &mut ControlFlow|
           -> anyhow::Result<()> {
+        let inspecting = state.inspector.is_open();
         *control_flow = input(&event, &mut state);
+        // The cursor points at texels while inspecting, and is hidden for
+        // looking around otherwise
+        if state.inspector.is_open() != inspecting {
+            let window = surface.ctx.window();
+            window.set_cursor_visible(state.inspector.is_open());
+        }
 
         match event {
             Event::NewEvents(..) => {
@@ -69,7 +117,8 @@ fn main() -> anyhow::Result<()> {
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::Resized(..) => {
                     let [width, height] = surface.size();
-                    state.back_buffer = context.back_buffer([width, height])?;
+                    back_buffer = context.back_buffer([width, height])?;
+                    state.post_process.resize(&mut context, [width, height])?;
                     state.camera.update_dimensions(width, height);
                 }
                 WindowEvent::CloseRequested => {
@@ -80,7 +129,7 @@ fn main() -> anyhow::Result<()> {
             Event::MainEventsCleared => {
                 let now = std::time::Instant::now();
                 let delta_t = now - last_input_read;
-                state.camera.take_input(&state.input);
+                camera_input(&mut state);
                 let delta_f = delta_t.as_micros() as f32 / 1_000_000.0;
                 state.camera.physics_tick(delta_f);
                 last_input_read = now;
@@ -89,12 +138,20 @@ fn main() -> anyhow::Result<()> {
                 let now = std::time::Instant::now();
                 let t = (now - start).as_secs_f32();
 
-                draw(t, &mut context, &mut state)
+                if let Some(recording) = &mut camera_recording {
+                    use std::io::Write;
+                    writeln!(recording, "{}", state.camera.key(t))?;
+                }
+
+                let output = context::Output::BackBuffer(&back_buffer);
+                draw(t, &mut context, &mut state, output)
                     .context("Failed to render")?;
 
                 surface.swap_buffers();
+                profiler::end_frame()?;
 
                 debug::glerr::print_gl_errors();
+                state.gl_messages.check();
             }
             _ => {}
         }
@@ -108,7 +165,7 @@ fn main() -> anyhow::Result<()> {
         match on_event(event, control_flow).context("Failed to process event") {
             Ok(_) => {}
             Err(e) => {
-                eprintln!("Error: {:?}", e);
+                tracing::error!("{:?}", e);
 
                 *control_flow = ControlFlow::Exit;
             }
@@ -117,34 +174,85 @@ fn main() -> anyhow::Result<()> {
 }
 
 struct AppState {
-    debugger: debug::Debugger,
+    gl_messages: debug::glerr::DebugMessages,
     blue_noise: noise::BlueNoise,
-    back_buffer: context::BackBuffer,
     camera: camera::Camera,
-    exposure: f32,
     input: input::Input,
+    inspector: debug::inspector::Inspector,
     ocean: ocean::Ocean,
+    ocean_lod: ocean::TessellationLod,
+    post_process: postprocess::PostProcess,
+    profiler_overlay: profiler::Overlay,
     render_water: bool,
     skybox: skybox::Skybox,
 }
 
+impl AppState {
+    fn new(
+        context: &mut context::Context,
+        size: [u32; 2],
+        skybox: skybox::Skybox,
+        ocean_seed: u64,
+        gl_messages: debug::glerr::DebugMessages,
+    ) -> anyhow::Result<Self> {
+        let [width, height] = size;
+        Ok(Self {
+            gl_messages,
+            blue_noise: noise::BlueNoise::new(context)?,
+            post_process: post_process(context, size)?,
+            profiler_overlay: profiler::Overlay::new(context)?,
+            camera: camera::Camera::new(width, height),
+            input: input::Input::default(),
+            inspector: debug::inspector::Inspector::new(context)?,
+            skybox,
+            ocean: ocean::Ocean::new(
+                context,
+                ocean::OceanGrid::from_env()?,
+                ocean_seed,
+            )?,
+            ocean_lod: Default::default(),
+            render_water: true,
+        })
+    }
+}
+
+fn post_process(
+    context: &mut context::Context,
+    size: [u32; 2],
+) -> anyhow::Result<postprocess::PostProcess> {
+    use postprocess::{
+        bloom::Bloom, dither::Dither, fxaa::Fxaa, tonemap::Tonemapper,
+    };
+
+    let mut post_process = postprocess::PostProcess::new(context, size)?;
+    post_process.push(Bloom::new(context, size)?);
+    post_process.push(Tonemapper::new(context)?);
+    post_process.push(Fxaa::new(context)?);
+    post_process.push(Dither::new(context)?);
+    Ok(post_process)
+}
+
 fn draw(
     t: f32,
     context: &mut context::Context,
     state: &mut AppState,
+    output: context::Output,
 ) -> anyhow::Result<()> {
     let AppState {
-        debugger,
         blue_noise,
-        back_buffer,
         camera,
-        exposure,
+        inspector,
         ocean,
+        ocean_lod,
+        post_process,
+        profiler_overlay,
         render_water,
         skybox,
         ..
     } = state;
 
+    let _frame = profiler::scope("frame");
+
     let mut pipeline_gate = context.new_pipeline_gate();
 
     let mut ocean_frame = match render_water {
@@ -154,72 +262,101 @@ fn draw(
 
     use luminance_front::pipeline::PipelineState;
 
+    let viewport = post_process.scene_buffer().size();
+
+    let scene_group = debug::DebugGroup::new("scene");
     pipeline_gate
         .pipeline(
-            back_buffer,
-            &PipelineState::new().enable_srgb(true),
+            post_process.scene_buffer(),
+            &PipelineState::new(),
             |mut pipeline, mut shader_gate| -> anyhow::Result<()> {
                 let view = camera.view();
                 let projection = camera.projection();
 
-                let view_projection = projection * view;
-
                 if let Some(ocean_frame) = &mut ocean_frame {
+                    let _scope = profiler::scope("ocean");
                     ocean_frame.render(
                         &pipeline,
                         &mut shader_gate,
-                        view_projection,
-                        camera.position(),
-                        Some(&mut skybox.sky_texture),
-                        *exposure,
+                        camera,
+                        viewport,
+                        skybox,
+                        *ocean_lod,
                     )?;
                 }
 
-                use luminance_front::{
-                    pixel::RG32F,
-                    texture::{Dim2, Texture},
-                };
-
-                let mut offset = -0.5;
-                let mut debug = |texture: &mut Texture<Dim2, RG32F>| {
-                    let result = debugger.render(
-                        &pipeline,
-                        &mut shader_gate,
-                        view_projection,
-                        glm::translation(&glm::Vec3::new(offset, 1., -2.)),
-                        Some(texture),
-                    );
-                    offset += 1.0;
-                    result
-                };
-
-                if let Some(frame) = &mut ocean_frame {
-                    for map in frame.offset_maps.iter_mut() {
-                        debug(*map)?;
-                    }
-                }
-                debug(&mut blue_noise.freq_texture)?;
-                debug(&mut blue_noise.noise_texture)?;
-
+                let _scope = profiler::scope("skybox");
                 skybox.render(
                     &mut pipeline,
                     &mut shader_gate,
                     view,
                     projection,
-                    *exposure,
                 )?;
 
                 Ok(())
             },
         )
         .into_result()?;
+    drop(scene_group);
+
+    {
+        let _scope = profiler::scope("post-process");
+        post_process.render(&mut pipeline_gate, output, blue_noise, t)?;
+    }
+
+    if let context::Output::BackBuffer(back_buffer) = output {
+        let _group = debug::DebugGroup::new("inspector");
+        let mut textures = ocean.inspectables();
+        textures.push(("blue noise", &mut blue_noise.texture));
+        textures.extend(skybox.inspectables());
+        inspector.render(
+            &mut pipeline_gate,
+            back_buffer,
+            viewport,
+            &mut textures,
+        )?;
+    }
+
+    if let (true, context::Output::BackBuffer(back_buffer)) =
+        (profiler::is_enabled(), output)
+    {
+        let _group = debug::DebugGroup::new("profiler overlay");
+        profiler_overlay.render(
+            &mut pipeline_gate,
+            back_buffer,
+            viewport,
+            &profiler::averages(),
+        )?;
+    }
 
     Ok(())
 }
 
+fn camera_input(state: &mut AppState) {
+    // The inspector has the mouse and keyboard to itself
+    let idle = input::Input::default();
+    let input = match state.inspector.is_open() {
+        true => &idle,
+        false => &state.input,
+    };
+    state.camera.take_input(input);
+}
+
 fn input(event: &Event<()>, state: &mut AppState) -> ControlFlow {
     state.input.update(event);
-    state.exposure *= 2.0_f32.powf(state.input.mouse().scroll());
+
+    let viewport = state.post_process.scene_buffer().size();
+    if state.inspector.input(event, viewport) {
+        return ControlFlow::Poll;
+    }
+
+    use postprocess::tonemap::Tonemapper;
+    if let (false, Some(tonemapper)) = (
+        state.inspector.is_open(),
+        state.post_process.pass_mut::<Tonemapper>(),
+    ) {
+        tonemapper.exposure *= 2.0_f32.powf(state.input.mouse().scroll());
+    }
 
     match event {
         Event::WindowEvent {
@@ -239,13 +376,80 @@ fn input(event: &Event<()>, state: &mut AppState) -> ControlFlow {
             (_, 18) => {
                 state.render_water = !state.render_water;
             }
+            (_, 20) => {
+                let term = state.ocean.shading_term.next();
+                tracing::info!(?term, "ocean shading term");
+                state.ocean.shading_term = term;
+            }
+            (_, 21) => {
+                if let Some(tonemapper) =
+                    state.post_process.pass_mut::<Tonemapper>()
+                {
+                    let operator = tonemapper.operator.next();
+                    tracing::info!(?operator, "tonemap operator");
+                    tonemapper.operator = operator;
+                }
+            }
+            (_, 22) => {
+                if let Some(tonemapper) =
+                    state.post_process.pass_mut::<Tonemapper>()
+                {
+                    let auto_exposure = &mut tonemapper.auto_exposure;
+                    auto_exposure.enabled = !auto_exposure.enabled;
+                    tracing::info!(
+                        enabled = auto_exposure.enabled,
+                        "auto exposure"
+                    );
+                }
+            }
+            (_, scancode @ 12..=13) => {
+                let lod = &mut state.ocean_lod;
+                let step = if *scancode == 12 { 2.0 } else { 0.5 };
+                lod.triangle_size = (lod.triangle_size * step).max(1.0);
+                tracing::info!(
+                    pixels = lod.triangle_size,
+                    "ocean triangle size"
+                );
+            }
+            (_, 23) => {
+                state.inspector.toggle();
+            }
+            (_, 24) => {
+                let messages = &state.gl_messages;
+                let severity = messages.minimum_severity().next();
+                tracing::info!(?severity, "minimum GL debug message severity");
+                messages.set_minimum_severity(severity);
+            }
+            (_, 25) => {
+                let enabled = !profiler::is_enabled();
+                if let Err(e) = profiler::set_enabled(enabled) {
+                    tracing::error!("failed to start profiling: {:#}", e);
+                }
+                tracing::info!(enabled = profiler::is_enabled(), "profiling");
+            }
+            (_, scancode @ 26..=27) => {
+                use postprocess::bloom::Bloom;
+                if let Some(bloom) = state.post_process.pass_mut::<Bloom>() {
+                    let step = if *scancode == 26 { 0.5 } else { 2.0 };
+                    bloom.intensity = (bloom.intensity * step).min(1.0);
+                    tracing::info!(intensity = bloom.intensity, "bloom");
+                }
+            }
+            // number keys toggle post-processing passes in chain order
+            (_, scancode @ 2..=10) => {
+                let index = *scancode as usize - 2;
+                if let Some((name, enabled)) = state.post_process.toggle(index)
+                {
+                    tracing::info!(enabled, "{}", name);
+                }
+            }
             (Some(VirtualKeyCode::Escape), _) => {
                 return ControlFlow::Exit;
             }
             _ => {}
         },
         Event::MainEventsCleared => {
-            state.camera.take_input(&state.input);
+            camera_input(state);
         }
         _ => {}
     }
@@ -1,60 +1,142 @@
 use crate::context::Context;
-use crate::fft::FftTexture;
-use luminance_front::{context::GraphicsContext, texture::Texture};
+use luminance_front::{
+    context::GraphicsContext,
+    pixel::RGBA32F,
+    texture::{Dim2, Texture},
+};
+use std::path::{Path, PathBuf};
+
+mod void_and_cluster;
+
+/// Frames after which the animation in blue_noise.glsl starts over. Keeps the
+/// frame number small enough to stay precise as a float.
+const ANIMATION_PERIOD: u64 = 4096;
+
+/// Side of the blue noise texture, which tiles.
+const SIZE: usize = 64;
+const SEED: u64 = 0x5eed;
+
+/// Bumped whenever the generator changes, to leave old cache files unused.
+const CACHE_VERSION: u32 = 1;
+
+pub type BlueNoiseTexture = Texture<Dim2, RGBA32F>;
 
 pub struct BlueNoise {
-    pub freq_texture: FftTexture,
-    pub noise_texture: FftTexture,
+    /// Four independent channels of blue noise, each uniformly distributed
+    /// in [0, 1), for sampling through blue_noise.glsl. Repeats when sampled
+    /// outside [0, 1].
+    pub texture: BlueNoiseTexture,
+}
+
+/// Where generated masks are kept between runs, `ARCHI_CACHE_DIR` if set.
+fn cache_dir() -> PathBuf {
+    std::env::var_os("ARCHI_CACHE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("archi"))
+}
+
+fn cache_path(dir: &Path, size: usize, channels: usize, seed: u64) -> PathBuf {
+    dir.join(format!(
+        "blue_noise_v{}_{}x{}_{}_{:x}.bin",
+        CACHE_VERSION, size, size, channels, seed
+    ))
+}
+
+fn load(path: &Path, len: usize) -> Option<Vec<f32>> {
+    let bytes = std::fs::read(path).ok()?;
+    if bytes.len() != len * 4 {
+        return None;
+    }
+
+    Some(
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    )
+}
+
+fn store(path: &Path, values: &[f32]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    std::fs::write(path, bytes)
+}
+
+/// A tileable `size` × `size` blue noise mask with 1 to 4 interleaved
+/// channels. Generating one takes a while, so it is cached on disk.
+pub fn mask(size: usize, channels: usize, seed: u64) -> Vec<f32> {
+    let path = cache_path(&cache_dir(), size, channels, seed);
+    if let Some(values) = load(&path, size * size * channels) {
+        return values;
+    }
+
+    tracing::info!("generating {}x{} blue noise...", size, size);
+    let values = void_and_cluster::mask(size, channels, seed);
+    if let Err(e) = store(&path, &values) {
+        tracing::warn!(
+            "could not cache blue noise at {}: {}",
+            path.display(),
+            e
+        );
+    }
+    values
 }
 
 impl BlueNoise {
     pub fn new(context: &mut Context) -> anyhow::Result<Self> {
-        use luminance::texture::{GenMipmaps, MagFilter, MinFilter, Sampler};
+        use luminance::texture::{
+            GenMipmaps, MagFilter, MinFilter, Sampler, Wrap,
+        };
         let sampler = Sampler {
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
             mag_filter: MagFilter::Nearest,
             min_filter: MinFilter::Nearest,
             ..Default::default()
         };
 
-        let size = 128;
-        let mut freq_texture = {
-            let mut texture = Texture::new(context, [size, size], 0, sampler)?;
-            let size = size as usize;
-            let mut pixels = Vec::with_capacity(size * size);
-            for x in 0..size {
-                for y in 0..size {
-                    pixels.push(match (x, y) {
-                        (0, 0) => (1., 1.),
-                        (x, y) => {
-                            let scale = 1. / 256.;
-                            let x = x as f32 * scale;
-                            let y = y as f32 * scale;
-                            let mag_sq = (x * x + y * y).sqrt();
-
-                            let val =
-                                || mag_sq * (rand::random::<f32>() * 2. - 1.);
-                            (val(), val())
-                        }
-                    });
-                }
-            }
-
-            texture.upload(GenMipmaps::No, &pixels)?;
-            texture
-        };
+        let pixels: Vec<_> = mask(SIZE, 4, SEED)
+            .chunks_exact(4)
+            .map(|texel| (texel[0], texel[1], texel[2], texel[3]))
+            .collect();
 
-        let mut fft = crate::fft::Fft::new(context, size)?;
-        let mut buf = crate::fft::Fft::framebuffer(context, size)?;
-        fft.render(
-            &mut context.new_pipeline_gate(),
-            &mut freq_texture,
-            &mut buf,
+        let size = SIZE as u32;
+        let mut texture = context.new_texture([size, size], 0, sampler)?;
+        texture.upload(GenMipmaps::No, &pixels)?;
+        crate::debug::capture::label_texture(
+            context,
+            &mut texture,
+            "blue noise",
         )?;
-        let noise_texture = buf.into_color_slot();
 
-        Ok(Self {
-            freq_texture,
-            noise_texture,
-        })
+        Ok(Self { texture })
+    }
+
+    /// Value of blue_noise_frame for shaders including blue_noise.glsl.
+    pub fn frame(index: u64) -> i32 {
+        (index % ANIMATION_PERIOD) as i32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cache_round_trip() {
+        let dir = std::env::temp_dir()
+            .join(format!("archi-noise-test-{}", std::process::id()));
+        let path = cache_path(&dir, 4, 2, 1);
+        let values: Vec<f32> = (0..32).map(|i| i as f32 / 32.0).collect();
+
+        store(&path, &values).unwrap();
+        assert_eq!(load(&path, values.len()), Some(values));
+        // A file of the wrong size is as good as none
+        assert_eq!(load(&path, 16), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
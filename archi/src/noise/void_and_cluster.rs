@@ -0,0 +1,311 @@
+//! Ulichney's void-and-cluster method for blue noise dither arrays. Pixels
+//! are ranked by the order in which they are added to an ever denser point
+//! set, each added where the set is sparsest. Distances wrap around, so the
+//! result tiles.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Standard deviation of the Gaussian that measures how crowded a pixel is.
+const SIGMA: f32 = 1.5;
+
+/// Fraction of pixels set in the initial random pattern.
+const INITIAL_DENSITY: f32 = 0.1;
+
+/// Sum of a Gaussian centered on every set pixel, on a torus.
+#[derive(Clone)]
+struct Energy {
+    size: usize,
+    radius: isize,
+    kernel: Vec<f32>,
+    values: Vec<f32>,
+}
+
+impl Energy {
+    fn new(size: usize) -> Self {
+        // Beyond three standard deviations the contribution is negligible.
+        let radius = (3.0 * SIGMA).ceil() as isize;
+        let width = 2 * radius + 1;
+        let kernel = (0..width * width)
+            .map(|i| {
+                let x = (i % width - radius) as f32;
+                let y = (i / width - radius) as f32;
+                (-(x * x + y * y) / (2.0 * SIGMA * SIGMA)).exp()
+            })
+            .collect();
+
+        Self {
+            size,
+            radius,
+            kernel,
+            values: vec![0.0; size * size],
+        }
+    }
+
+    /// Add (or with a negative `sign`, remove) the point at `index`.
+    fn splat(&mut self, index: usize, sign: f32) {
+        let size = self.size as isize;
+        let width = 2 * self.radius + 1;
+        let (px, py) =
+            ((index % self.size) as isize, (index / self.size) as isize);
+
+        for dy in -self.radius..=self.radius {
+            let y = (py + dy).rem_euclid(size);
+            for dx in -self.radius..=self.radius {
+                let x = (px + dx).rem_euclid(size);
+                let k = self.kernel
+                    [((dy + self.radius) * width + dx + self.radius) as usize];
+                self.values[(y * size + x) as usize] += sign * k;
+            }
+        }
+    }
+
+    /// The set pixel with the most energy around it.
+    fn tightest_cluster(&self, pattern: &[bool]) -> usize {
+        self.extreme(pattern, true, |a, b| a > b)
+    }
+
+    /// The unset pixel with the least energy around it.
+    fn largest_void(&self, pattern: &[bool]) -> usize {
+        self.extreme(pattern, false, |a, b| a < b)
+    }
+
+    fn extreme(
+        &self,
+        pattern: &[bool],
+        set: bool,
+        better: impl Fn(f32, f32) -> bool,
+    ) -> usize {
+        let mut best: Option<usize> = None;
+        for (i, &value) in self.values.iter().enumerate() {
+            if pattern[i] == set
+                && best.is_none_or(|best| better(value, self.values[best]))
+            {
+                best = Some(i);
+            }
+        }
+        best.expect("pattern has no pixels in the wanted state")
+    }
+}
+
+/// Rank of every pixel of a `size` × `size` dither array, each of
+/// `0..size * size` exactly once. Thresholding at any rank gives a blue noise
+/// point set.
+pub fn ranks(size: usize, seed: u64) -> Vec<u32> {
+    assert!(size.is_power_of_two(), "size must be a power of two");
+
+    let count = size * size;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut energy = Energy::new(size);
+    let mut pattern = vec![false; count];
+
+    // Random initial points
+    let initial = ((count as f32 * INITIAL_DENSITY) as usize).max(1);
+    let mut set = 0;
+    while set < initial {
+        let i = rng.gen_range(0..count);
+        if !pattern[i] {
+            pattern[i] = true;
+            energy.splat(i, 1.0);
+            set += 1;
+        }
+    }
+
+    // Even them out by moving the most crowded point into the emptiest spot,
+    // until that spot is where the point came from
+    loop {
+        let cluster = energy.tightest_cluster(&pattern);
+        pattern[cluster] = false;
+        energy.splat(cluster, -1.0);
+
+        let void = energy.largest_void(&pattern);
+        pattern[void] = true;
+        energy.splat(void, 1.0);
+
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; count];
+
+    // The initial points are ranked by removing them, most crowded first
+    {
+        let mut pattern = pattern.clone();
+        let mut energy = energy.clone();
+        for rank in (0..initial).rev() {
+            let cluster = energy.tightest_cluster(&pattern);
+            pattern[cluster] = false;
+            energy.splat(cluster, -1.0);
+            ranks[cluster] = rank as u32;
+        }
+    }
+
+    // The rest by filling the emptiest spot. Past half, Ulichney switches to
+    // removing the tightest cluster of unset pixels instead, but the energy
+    // of the unset pixels is a constant minus that of the set ones, so it
+    // picks the same pixel.
+    for rank in initial..count {
+        let void = energy.largest_void(&pattern);
+        pattern[void] = true;
+        energy.splat(void, 1.0);
+        ranks[void] = rank as u32;
+    }
+
+    ranks
+}
+
+/// A `size` × `size` mask of `channels` independent dither arrays,
+/// interleaved, with values uniformly spread over [0, 1).
+pub fn mask(size: usize, channels: usize, seed: u64) -> Vec<f32> {
+    assert!((1..=4).contains(&channels), "1 to 4 channels are supported");
+
+    let count = size * size;
+    let channel_ranks: Vec<_> = (0..channels as u64)
+        .map(|channel| ranks(size, seed.wrapping_add(channel)))
+        .collect();
+
+    let mut values = Vec::with_capacity(count * channels);
+    for i in 0..count {
+        for ranks in &channel_ranks {
+            values.push((ranks[i] as f32 + 0.5) / count as f32);
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    // Power spectrum of a square signal with its mean removed, by a
+    // separable DFT. The DFT treats the signal as periodic, so seams between
+    // tiles count like any other part of it.
+    fn power_spectrum(values: &[f32], size: usize) -> Vec<f32> {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+
+        let dft_rows = |input: &[(f32, f32)]| {
+            let mut output = vec![(0.0, 0.0); input.len()];
+            for y in 0..size {
+                for k in 0..size {
+                    let (mut re, mut im) = (0.0, 0.0);
+                    for x in 0..size {
+                        let (a, b) = input[y * size + x];
+                        let angle = -TAU * (k * x) as f32 / size as f32;
+                        let (s, c) = angle.sin_cos();
+                        re += a * c - b * s;
+                        im += a * s + b * c;
+                    }
+                    output[y * size + k] = (re, im);
+                }
+            }
+            output
+        };
+        let transpose = |input: &[(f32, f32)]| {
+            let mut output = input.to_vec();
+            for y in 0..size {
+                for x in 0..size {
+                    output[x * size + y] = input[y * size + x];
+                }
+            }
+            output
+        };
+
+        let signal: Vec<_> = values.iter().map(|v| (v - mean, 0.0)).collect();
+        let spectrum = transpose(&dft_rows(&transpose(&dft_rows(&signal))));
+        spectrum.iter().map(|(re, im)| re * re + im * im).collect()
+    }
+
+    // Mean power at frequencies below `cutoff`, relative to the mean power
+    // over all frequencies.
+    fn low_frequency_ratio(values: &[f32], size: usize, cutoff: f32) -> f32 {
+        let power = power_spectrum(values, size);
+        let half = size as isize / 2;
+
+        let (mut low, mut low_count) = (0.0, 0);
+        for (i, p) in power.iter().enumerate() {
+            let fx =
+                ((i % size) as isize + half).rem_euclid(size as isize) - half;
+            let fy =
+                ((i / size) as isize + half).rem_euclid(size as isize) - half;
+            let radius = ((fx * fx + fy * fy) as f32).sqrt();
+            if radius > 0.0 && radius < cutoff {
+                low += p;
+                low_count += 1;
+            }
+        }
+
+        let total = power.iter().sum::<f32>() / (power.len() - 1) as f32;
+        low / low_count as f32 / total
+    }
+
+    #[test]
+    fn every_rank_once() {
+        for &size in &[1, 2, 8, 32] {
+            let mut ranks = ranks(size, 7);
+            ranks.sort_unstable();
+            assert!(ranks.iter().enumerate().all(|(i, &r)| r == i as u32));
+        }
+    }
+
+    #[test]
+    fn deterministic_for_seed() {
+        assert_eq!(ranks(16, 3), ranks(16, 3));
+        assert_ne!(ranks(16, 3), ranks(16, 4));
+    }
+
+    #[test]
+    fn low_frequencies_suppressed() {
+        let size = 32;
+        let cutoff = size as f32 / 8.0;
+
+        let blue = mask(size, 1, 1);
+        let ratio = low_frequency_ratio(&blue, size, cutoff);
+        assert!(ratio < 0.01, "low frequency power ratio {}", ratio);
+
+        // White noise as a control, to show the measure means something
+        let mut rng = StdRng::seed_from_u64(1);
+        let white: Vec<f32> = (0..size * size).map(|_| rng.gen()).collect();
+        let ratio = low_frequency_ratio(&white, size, cutoff);
+        assert!(ratio > 0.5, "white noise low frequency ratio {}", ratio);
+    }
+
+    #[test]
+    fn thresholded_sets_are_blue() {
+        // Any threshold should give a blue point set, not only the full mask
+        let size = 32;
+        let mask = mask(size, 1, 5);
+        for &threshold in &[0.1, 0.5, 0.9] {
+            let points: Vec<f32> = mask
+                .iter()
+                .map(|&v| if v < threshold { 1.0 } else { 0.0 })
+                .collect();
+            let ratio = low_frequency_ratio(&points, size, size as f32 / 8.0);
+            assert!(ratio < 0.2, "ratio {} at {}", ratio, threshold);
+        }
+    }
+
+    #[test]
+    fn channels_uniform_and_distinct() {
+        let size = 16;
+        let channels = 4;
+        let mask = mask(size, channels, 9);
+        assert_eq!(mask.len(), size * size * channels);
+
+        for channel in 0..channels {
+            let values: Vec<f32> = mask
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .copied()
+                .collect();
+            let mean = values.iter().sum::<f32>() / values.len() as f32;
+            assert!((mean - 0.5).abs() < 1e-6);
+            assert!(values.iter().all(|&v| v > 0.0 && v < 1.0));
+        }
+
+        let first: Vec<_> = mask.iter().step_by(channels).collect();
+        let second: Vec<_> = mask.iter().skip(1).step_by(channels).collect();
+        assert_ne!(first, second);
+    }
+}
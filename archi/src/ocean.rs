@@ -1,15 +1,21 @@
 use crate::context::Context;
+use crate::debug::{
+    capture::{label_framebuffer, label_texture},
+    inspector::Inspectable,
+};
 use luminance_derive::UniformInterface;
 use luminance_front::{
     context::GraphicsContext,
     framebuffer::Framebuffer,
     pipeline::{Pipeline, PipelineGate, TextureBinding},
-    pixel::{Floating, RG32F, RGB32F, RGBA32F},
+    pixel::{Floating, RGBA32F},
     shader::{Program, Uniform},
     shading_gate::ShadingGate,
-    tess::{Mode, Tess},
-    texture::{Dim2, GenMipmaps, Texture},
+    tess::{Mode, Tess, View},
+    texture::{Cubemap, Dim2, GenMipmaps, Texture},
 };
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::ops::Range;
 
 const QUAD_VS_SRC: crate::shader::ShaderSource =
     crate::shader_source!("./shaders/quad.vert");
@@ -51,11 +57,23 @@ struct H0k {
 
 const N: u32 = 0x200;
 
+/// Largest difference between the compute and fragment FFTs, relative to the
+/// size of the waves, before the compute one is not trusted.
+const FFT_TOLERANCE: f32 = 1e-3;
+
+/// Largest difference between the half and full precision FFTs, relative to
+/// the size of the waves, before falling back to full precision. Half floats
+/// have about three decimal digits, which the passes lose some of.
+const HALF_FFT_TOLERANCE: f32 = 1e-2;
+
 impl H0k {
-    pub fn new(context: &mut Context) -> anyhow::Result<Self> {
+    /// The noise the waves are made from is drawn from `seed`.
+    pub fn new(context: &mut Context, seed: u64) -> anyhow::Result<Self> {
         let size = [N, N];
-        let framebuffer =
+        let mut framebuffer =
             Framebuffer::new(context, size, 0, Default::default())?;
+        label_framebuffer(context, &framebuffer, "h0k")?;
+        label_texture(context, framebuffer.color_slot(), "h0k")?;
 
         let shader = crate::shader::from_sources(
             context,
@@ -78,9 +96,8 @@ impl H0k {
             let mut texture = Texture::new(context, size, 0, sampler)?;
             let length = N * N;
             let mut pixels = Vec::with_capacity(length as usize);
-            let mut rng = rand::thread_rng();
+            let mut rng = StdRng::seed_from_u64(seed);
             for _ in 0..length {
-                use rand::Rng;
                 let [a, b, c, d]: [f32; 4] = rng.gen();
                 let a = (-2.0 * a.ln()).sqrt();
                 let b = (-2.0 * b.ln()).sqrt();
@@ -96,6 +113,7 @@ impl H0k {
             }
 
             texture.upload(GenMipmaps::No, &pixels)?;
+            label_texture(context, &mut texture, "gaussian noise")?;
             texture
         };
 
@@ -172,19 +190,21 @@ struct HktInterface {
     time: Uniform<f32>,
 }
 
-type HktTexture = Texture<Dim2, RG32F>;
+type HktTexture = FftTexture;
 
 pub struct Hkt {
     tess: Tess<()>,
     shader: Program<(), (), HktInterface>,
-    pub framebuffer: Framebuffer<Dim2, (RG32F, RG32F, RG32F), ()>,
+    pub framebuffer: FftFramebuffer,
 }
 
 impl Hkt {
     fn new(context: &mut Context) -> anyhow::Result<Self> {
         let size = [N, N];
-        let framebuffer =
+        let mut framebuffer =
             Framebuffer::new(context, size, 0, Default::default())?;
+        label_framebuffer(context, &framebuffer, "hkt")?;
+        label_texture(context, framebuffer.color_slot(), "hkt")?;
         let shader = crate::shader::from_sources(
             context,
             None,
@@ -207,7 +227,7 @@ impl Hkt {
         pipline_gate: &mut PipelineGate,
         time: f32,
         h0k_texture: &mut H0kTexture,
-    ) -> anyhow::Result<[&mut HktTexture; 3]> {
+    ) -> anyhow::Result<&mut HktTexture> {
         let Self {
             framebuffer,
             shader,
@@ -237,74 +257,427 @@ impl Hkt {
             )
             .into_result()?;
 
-        let (x, y, z) = framebuffer.color_slot();
-        Ok([x, y, z])
+        Ok(framebuffer.color_slot())
     }
 }
 
 #[derive(UniformInterface)]
 pub struct OceanShaderInterface {
     #[uniform(unbound)]
-    xmap: Uniform<TextureBinding<Dim2, Floating>>,
-    #[uniform(unbound)]
-    ymap: Uniform<TextureBinding<Dim2, Floating>>,
-    #[uniform(unbound)]
-    zmap: Uniform<TextureBinding<Dim2, Floating>>,
+    displacement_map: Uniform<TextureBinding<Dim2, Floating>>,
 
     view_projection: Uniform<[[f32; 4]; 4]>,
+    #[uniform(unbound)]
     camera_offset: Uniform<[f32; 2]>,
 
-    sky_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    // Patches only
+    #[uniform(unbound)]
+    tessellation_scale: Uniform<f32>,
+    #[uniform(unbound)]
+    max_tessellation_level: Uniform<f32>,
+
+    // Projected grid only
+    #[uniform(unbound)]
+    inverse_view_projection: Uniform<[[f32; 4]; 4]>,
+    #[uniform(unbound)]
+    grid_size: Uniform<[i32; 2]>,
+    #[uniform(unbound)]
+    overscan: Uniform<f32>,
+    #[uniform(unbound)]
+    max_distance: Uniform<f32>,
+    #[uniform(unbound)]
+    displacement_fade: Uniform<f32>,
+
+    sky_texture: Uniform<TextureBinding<Cubemap, Floating>>,
+    specular_1: Uniform<TextureBinding<Cubemap, Floating>>,
+    specular_2: Uniform<TextureBinding<Cubemap, Floating>>,
+    specular_3: Uniform<TextureBinding<Cubemap, Floating>>,
+    specular_4: Uniform<TextureBinding<Cubemap, Floating>>,
+    specular_5: Uniform<TextureBinding<Cubemap, Floating>>,
+    specular_levels: Uniform<i32>,
+    irradiance_sh: Uniform<TextureBinding<Dim2, Floating>>,
     camera_pos: Uniform<[f32; 3]>,
-    exposure: Uniform<f32>,
+
+    sun_direction: Uniform<[f32; 3]>,
+    sun_color: Uniform<[f32; 3]>,
+    sun_angular_radius: Uniform<f32>,
+    slope_variance: Uniform<f32>,
+
+    fog_extinction: Uniform<[f32; 3]>,
+    fog_height_falloff: Uniform<f32>,
+    fog_base_height: Uniform<f32>,
+    fog_sun_scattering: Uniform<f32>,
+    fog_anisotropy: Uniform<f32>,
+
+    ior: Uniform<f32>,
+    water_absorption: Uniform<[f32; 3]>,
+    water_scattering: Uniform<[f32; 3]>,
+    subsurface_color: Uniform<[f32; 3]>,
+    subsurface_height: Uniform<f32>,
+    shading_term: Uniform<i32>,
+}
+
+/// Optical properties of the water body.
+#[derive(Debug, Clone, Copy)]
+pub struct Water {
+    /// Index of refraction of water relative to air.
+    pub ior: f32,
+    /// Absorption coefficient per meter, for each colour channel.
+    pub absorption: glm::Vec3,
+    /// Scattering coefficient per meter, for each colour channel.
+    pub scattering: glm::Vec3,
+    /// Colour of sunlight shining through thin wave crests.
+    pub subsurface_color: glm::Vec3,
+    /// Height above the mean water level at which a crest is thin enough to
+    /// glow at full strength.
+    pub subsurface_height: f32,
+}
+
+impl Default for Water {
+    fn default() -> Self {
+        Self {
+            ior: 1.33,
+            absorption: glm::vec3(0.45, 0.066, 0.018),
+            scattering: glm::vec3(0.01, 0.02, 0.03),
+            subsurface_color: glm::vec3(0.1, 0.4, 0.35),
+            subsurface_height: 4.0,
+        }
+    }
+}
+
+/// Selects which part of the ocean shading ends up on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadingTerm {
+    Combined = 0,
+    Fresnel = 1,
+    Reflection = 2,
+    Sun = 3,
+    WaterBody = 4,
+    Subsurface = 5,
+}
+
+impl ShadingTerm {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Combined => Self::Fresnel,
+            Self::Fresnel => Self::Reflection,
+            Self::Reflection => Self::Sun,
+            Self::Sun => Self::WaterBody,
+            Self::WaterBody => Self::Subsurface,
+            Self::Subsurface => Self::Combined,
+        }
+    }
+}
+
+impl Default for ShadingTerm {
+    fn default() -> Self {
+        Self::Combined
+    }
 }
 
 type OceanShader = Program<(), (), OceanShaderInterface>;
 
-use crate::fft::{Fft, FftFramebuffer, FftTexture};
+/// How the ocean surface is laid out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OceanGrid {
+    /// A fixed square of tessellated patches around the camera.
+    Patches,
+    /// A grid over the screen projected onto the water and displaced per
+    /// vertex, which reaches the horizon in every direction.
+    Projected {
+        /// Vertices along each axis of the screen.
+        resolution: [u32; 2],
+    },
+    /// A fixed square of vertices around the camera, displaced per vertex.
+    /// For drivers without tessellation shaders.
+    Dense,
+}
+
+impl OceanGrid {
+    /// The grid named by `$ARCHI_OCEAN_GRID`, `patches`, `projected` or
+    /// `dense`, defaulting to patches.
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("ARCHI_OCEAN_GRID") {
+            Err(_) => Ok(Default::default()),
+            Ok(name) => match name.as_str() {
+                "patches" => Ok(Self::Patches),
+                "projected" => Ok(Self::Projected {
+                    resolution: [512, 512],
+                }),
+                "dense" => Ok(Self::Dense),
+                _ => anyhow::bail!("Unknown ocean grid {:?}", name),
+            },
+        }
+    }
+}
+
+impl Default for OceanGrid {
+    fn default() -> Self {
+        Self::Patches
+    }
+}
+
+/// How finely the patches are tessellated, from the size their edges take up
+/// on screen.
+#[derive(Debug, Clone, Copy)]
+pub struct TessellationLod {
+    /// Edge length in pixels that tessellated triangles aim for.
+    pub triangle_size: f32,
+    /// Upper bound on the tessellation level, on top of the one set by the
+    /// driver.
+    pub max_level: f32,
+}
+
+impl TessellationLod {
+    /// Tessellation level of an edge of length one at distance one, given
+    /// the vertical scale of the projection and the height of the viewport.
+    fn scale(&self, projection: &glm::Mat4, viewport_height: u32) -> f32 {
+        let pixels_per_meter =
+            projection[(1, 1)] * viewport_height as f32 / 2.0;
+        pixels_per_meter / self.triangle_size
+    }
+}
+
+impl Default for TessellationLod {
+    fn default() -> Self {
+        Self {
+            triangle_size: 8.0,
+            max_level: 64.0,
+        }
+    }
+}
+
+/// Fraction of the screen the projected grid extends past each edge.
+const PROJECTED_OVERSCAN: f32 = 0.1;
+/// Distance at which the projected grid meets the horizon.
+const PROJECTED_MAX_DISTANCE: f32 = 20_000.0;
+/// Distance beyond which the projected grid is too coarse to be displaced.
+const PROJECTED_DISPLACEMENT_FADE: f32 = 2_000.0;
+
+/// Cells along each side of the dense grid, as in ocean_dense.vert.
+const DENSE_GRID_SIZE: u32 = 0x200;
+
+/// Patches along each side of the patch grid, as in ocean.vert.
+const PATCH_GRID_SIZE: u32 = 0x100;
+/// Side of a patch in meters, as in ocean.vert.
+const PATCH_SCALE: f32 = 8.0;
+/// Patches along each side of a chunk, the unit the grid is culled in.
+const PATCH_CHUNK_SIZE: u32 = 0x10;
+/// Bound on how far the waves move the surface in any direction. Chunks are
+/// grown by it before culling, so that nothing displaced into view is lost.
+const PATCH_DISPLACEMENT_BOUND: f32 = 16.0;
+
+/// Index ranges of the chunks of the patch grid that may be visible, with
+/// neighbouring ranges merged into one.
+fn visible_patch_chunks(
+    frustum: &Frustum,
+    camera_pos: &glm::Vec3,
+) -> Vec<Range<usize>> {
+    let chunks = PATCH_GRID_SIZE / PATCH_CHUNK_SIZE;
+    let chunk_len = (PATCH_CHUNK_SIZE * PATCH_CHUNK_SIZE * 4) as usize;
+    let chunk_side = PATCH_CHUNK_SIZE as f32 * PATCH_SCALE;
+
+    // Same as in ocean.vert
+    let origin = glm::vec2(camera_pos.x, camera_pos.z) / PATCH_SCALE;
+    let origin = glm::floor(&origin) * PATCH_SCALE;
+    let grid_min = origin
+        - glm::vec2(1.0, 1.0) * (PATCH_GRID_SIZE / 2) as f32 * PATCH_SCALE;
+
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for chunk_x in 0..chunks {
+        for chunk_y in 0..chunks {
+            let min = grid_min
+                + glm::vec2(chunk_x as f32, chunk_y as f32) * chunk_side;
+            let bound = PATCH_DISPLACEMENT_BOUND;
+            let visible = frustum.intersects_aabb(
+                &glm::vec3(min.x - bound, -bound, min.y - bound),
+                &glm::vec3(
+                    min.x + chunk_side + bound,
+                    bound,
+                    min.y + chunk_side + bound,
+                ),
+            );
+            if !visible {
+                continue;
+            }
+
+            let start = (chunk_x * chunks + chunk_y) as usize * chunk_len;
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end += chunk_len,
+                _ => ranges.push(start..start + chunk_len),
+            }
+        }
+    }
+    ranges
+}
+
+enum Geometry {
+    Patches(Tess<(), u32>),
+    Projected {
+        tess: Tess<(), u32>,
+        resolution: [u32; 2],
+    },
+    Dense(Tess<(), u32>),
+}
+
+use crate::camera::{Camera, Frustum};
+use crate::fft::{Fft, FftBackend, FftFramebuffer, FftPrecision, FftTexture};
+use crate::skybox::Skybox;
 pub struct Ocean {
     pub h0k_texture: H0kTexture,
     pub hkt: Hkt,
-    offset_buffers: [FftFramebuffer; 3],
+    displacement_buffer: FftFramebuffer,
     pub fft: Fft,
     shader: OceanShader,
-    tess: Tess<(), u32>,
+    geometry: Geometry,
+    /// Slope variance of the waves too small to be captured by the
+    /// displacement maps. Roughens the reflection of the sun.
+    pub slope_variance: f32,
+    pub water: Water,
+    pub shading_term: ShadingTerm,
 }
 
 impl Ocean {
-    pub fn new(context: &mut Context) -> anyhow::Result<Self> {
-        let mut h0k = H0k::new(context)?;
+    /// Waves from the noise drawn from `seed`, so that the same seed gives
+    /// the same sea.
+    pub fn new(
+        context: &mut Context,
+        grid: OceanGrid,
+        seed: u64,
+    ) -> anyhow::Result<Self> {
+        let mut h0k = H0k::new(context, seed)?;
         h0k.render(&mut context.new_pipeline_gate())?;
 
-        let hkt = Hkt::new(context)?;
-        let offset_buffers = [
-            Fft::framebuffer(context, N)?,
-            Fft::framebuffer(context, N)?,
-            Fft::framebuffer(context, N)?,
-        ];
-        let fft = Fft::new(context, N)?;
-        let shader = crate::shader::from_sources(
+        let mut hkt = Hkt::new(context)?;
+        let mut displacement_buffer = Fft::framebuffer(context, [N, N])?;
+        label_framebuffer(context, &displacement_buffer, "displacement")?;
+        label_texture(
             context,
-            Some((
-                crate::shader_source!("./shaders/ocean.tesc"),
-                crate::shader_source!("./shaders/ocean.tese"),
-            )),
-            crate::shader_source!("./shaders/ocean.vert"),
-            None, // Some(crate::shader_source!("./shaders/ocean.geom")),
-            crate::shader_source!("./shaders/ocean.frag"),
+            displacement_buffer.color_slot(),
+            "displacement",
         )?;
-
-        let tess = crate::grid::square_patch_grid(context, 0x100)?;
-
-        let h0k_texture = h0k.into_texture();
+        let mut fft = Fft::new(context, [N, N])?;
+
+        let mut h0k_texture = h0k.into_texture();
+
+        let check_backends = fft.backend() == FftBackend::Compute;
+        let check_precisions = fft.precision() == FftPrecision::Half;
+        if check_backends || check_precisions {
+            let mut buffers = [
+                Fft::framebuffer(context, [N, N])?,
+                Fft::framebuffer(context, [N, N])?,
+            ];
+            let [a, b] = &mut buffers;
+            let spectrum = hkt.render(
+                &mut context.new_pipeline_gate(),
+                0.0,
+                &mut h0k_texture,
+            )?;
+
+            if check_precisions {
+                let error = fft.compare_precisions(
+                    &mut context.new_pipeline_gate(),
+                    spectrum,
+                    [&mut *a, &mut *b],
+                )?;
+                // Also catches overflow, which makes the error infinite
+                if error.max <= HALF_FFT_TOLERANCE {
+                    tracing::info!(%error, "half precision FFT");
+                } else {
+                    tracing::warn!(
+                        %error,
+                        "half precision FFT is off by more than {:.0e}, using \
+                         full precision",
+                        HALF_FFT_TOLERANCE
+                    );
+                    fft.use_full_precision();
+                }
+            }
+            if check_backends {
+                let error = fft.compare_backends(
+                    &mut context.new_pipeline_gate(),
+                    spectrum,
+                    [a, b],
+                )?;
+                if error.max > FFT_TOLERANCE {
+                    tracing::warn!(
+                        %error,
+                        "compute FFT is off by more than {:.0e}, using \
+                         fragment shaders",
+                        FFT_TOLERANCE
+                    );
+                    fft.set_backend(FftBackend::Fragment)?;
+                } else {
+                    tracing::info!(%error, "compute FFT matches fragment shaders");
+                }
+            }
+        }
+
+        let grid = match grid {
+            OceanGrid::Patches if !context.capabilities.tessellation => {
+                tracing::warn!(
+                    "no tessellation shaders, using a dense ocean grid"
+                );
+                OceanGrid::Dense
+            }
+            grid => grid,
+        };
+        let (shader, geometry) = match grid {
+            OceanGrid::Patches => (
+                crate::shader::from_sources(
+                    context,
+                    Some((
+                        crate::shader_source!("./shaders/ocean.tesc"),
+                        crate::shader_source!("./shaders/ocean.tese"),
+                    )),
+                    crate::shader_source!("./shaders/ocean.vert"),
+                    None, // Some(crate::shader_source!("./shaders/ocean.geom")),
+                    crate::shader_source!("./shaders/ocean.frag"),
+                )?,
+                Geometry::Patches(crate::grid::square_patch_grid(
+                    context,
+                    PATCH_GRID_SIZE,
+                    PATCH_CHUNK_SIZE,
+                )?),
+            ),
+            OceanGrid::Projected { resolution } => (
+                crate::shader::from_sources(
+                    context,
+                    None,
+                    crate::shader_source!("./shaders/ocean_projected.vert"),
+                    None,
+                    crate::shader_source!("./shaders/ocean.frag"),
+                )?,
+                Geometry::Projected {
+                    tess: crate::grid::triangle_grid(context, resolution)?,
+                    resolution,
+                },
+            ),
+            OceanGrid::Dense => (
+                crate::shader::from_sources(
+                    context,
+                    None,
+                    crate::shader_source!("./shaders/ocean_dense.vert"),
+                    None,
+                    crate::shader_source!("./shaders/ocean.frag"),
+                )?,
+                Geometry::Dense(crate::grid::triangle_grid(
+                    context,
+                    [DENSE_GRID_SIZE + 1, DENSE_GRID_SIZE + 1],
+                )?),
+            ),
+        };
 
         Ok(Self {
             h0k_texture,
             hkt,
-            offset_buffers,
+            displacement_buffer,
             fft,
             shader,
-            tess,
+            geometry,
+            slope_variance: 0.002,
+            water: Default::default(),
+            shading_term: Default::default(),
         })
     }
 
@@ -316,34 +689,53 @@ impl Ocean {
         let Self {
             h0k_texture,
             hkt,
-            offset_buffers,
+            displacement_buffer,
             fft,
             shader,
-            tess,
+            geometry,
+            slope_variance,
+            water,
+            shading_term,
         } = self;
 
-        let offset_maps = {
-            let [hkt_x, hkt_y, hkt_z] =
-                hkt.render(pipeline_gate, time, h0k_texture)?;
-            let [xmap, ymap, zmap] = offset_buffers;
-            let xmap = fft.render(pipeline_gate, hkt_x, xmap)?;
-            let ymap = fft.render(pipeline_gate, hkt_y, ymap)?;
-            let zmap = fft.render(pipeline_gate, hkt_z, zmap)?;
-            [xmap, ymap, zmap]
+        let spectrum = {
+            let _scope = crate::profiler::scope("hkt");
+            hkt.render(pipeline_gate, time, h0k_texture)?
+        };
+        let displacement_map = {
+            let _scope = crate::profiler::scope("fft");
+            fft.render(pipeline_gate, spectrum, displacement_buffer)?
         };
 
         Ok(OceanFrame {
             shader,
-            tess,
-            offset_maps,
+            geometry,
+            displacement_map,
+            slope_variance: *slope_variance,
+            water: *water,
+            shading_term: *shading_term,
         })
     }
+
+    pub fn inspectables(&mut self) -> Vec<Inspectable> {
+        let mut textures: Vec<Inspectable> = vec![
+            ("h0k", &mut self.h0k_texture),
+            ("hkt", self.hkt.framebuffer.color_slot()),
+            ("displacement", self.displacement_buffer.color_slot()),
+        ];
+        textures.extend(self.fft.inspectables());
+        textures
+    }
 }
 
 pub struct OceanFrame<'a> {
     shader: &'a mut OceanShader,
-    tess: &'a mut Tess<(), u32>,
-    pub offset_maps: [&'a mut FftTexture; 3],
+    geometry: &'a mut Geometry,
+    /// Displacement of the surface along x, z and y in r, g and b.
+    pub displacement_map: &'a mut FftTexture,
+    slope_variance: f32,
+    water: Water,
+    shading_term: ShadingTerm,
 }
 
 impl<'a> OceanFrame<'a> {
@@ -351,38 +743,164 @@ impl<'a> OceanFrame<'a> {
         &mut self,
         pipeline: &Pipeline,
         shader_gate: &mut ShadingGate,
-        view_projection: glm::Mat4,
-        camera_pos: glm::Vec3,
-        sky_texture: Option<&mut Texture<Dim2, RGB32F>>,
-        exposure: f32,
+        camera: &Camera,
+        viewport: [u32; 2],
+        skybox: &mut Skybox,
+        lod: TessellationLod,
     ) -> anyhow::Result<()> {
+        let projection = camera.projection();
+        let view_projection = projection * camera.view();
+        let camera_pos = camera.position();
+
         let Self {
             shader,
-            tess,
-            offset_maps: [xmap, ymap, zmap],
+            geometry,
+            displacement_map,
+            slope_variance,
+            water,
+            shading_term,
         } = self;
 
-        let xmap = pipeline.bind_texture(xmap)?;
-        let ymap = pipeline.bind_texture(ymap)?;
-        let zmap = pipeline.bind_texture(zmap)?;
+        let displacement_map = pipeline.bind_texture(displacement_map)?;
+
+        let Skybox {
+            sky_texture,
+            specular,
+            irradiance_sh,
+            sun,
+            fog,
+            ..
+        } = skybox;
+        let sky_texture = pipeline.bind_texture(sky_texture)?;
+        let specular = specular
+            .iter_mut()
+            .map(|level| pipeline.bind_texture(level))
+            .collect::<Result<Vec<_>, _>>()?;
+        let irradiance_sh = pipeline.bind_texture(irradiance_sh)?;
 
         shader_gate.shade(shader, |mut iface, uni, mut render_gate| {
             iface.set(&uni.view_projection, view_projection.into());
-            iface.set(&uni.xmap, xmap.binding());
-            iface.set(&uni.ymap, ymap.binding());
-            iface.set(&uni.zmap, zmap.binding());
+            iface.set(&uni.displacement_map, displacement_map.binding());
 
             iface.set(&uni.camera_pos, camera_pos.into());
-            if let Some(texture) = sky_texture {
-                let texture = pipeline.bind_texture(texture)?;
-                iface.set(&uni.sky_texture, texture.binding());
+            iface.set(&uni.sky_texture, sky_texture.binding());
+            let specular_uniforms = [
+                &uni.specular_1,
+                &uni.specular_2,
+                &uni.specular_3,
+                &uni.specular_4,
+                &uni.specular_5,
+            ];
+            for (&uniform, level) in specular_uniforms.iter().zip(&specular) {
+                iface.set(uniform, level.binding());
+            }
+            iface.set(
+                &uni.specular_levels,
+                crate::skybox::SPECULAR_LEVELS as i32,
+            );
+            iface.set(&uni.irradiance_sh, irradiance_sh.binding());
+
+            iface.set(&uni.sun_direction, sun.direction.into());
+            iface.set(&uni.sun_color, sun.color.into());
+            iface.set(&uni.sun_angular_radius, sun.angular_radius);
+            iface.set(&uni.slope_variance, *slope_variance);
+
+            iface.set(&uni.fog_extinction, fog.extinction.into());
+            iface.set(&uni.fog_height_falloff, fog.height_falloff);
+            iface.set(&uni.fog_base_height, fog.base_height);
+            iface.set(&uni.fog_sun_scattering, fog.sun_scattering);
+            iface.set(&uni.fog_anisotropy, fog.anisotropy);
+
+            iface.set(&uni.ior, water.ior);
+            iface.set(&uni.water_absorption, water.absorption.into());
+            iface.set(&uni.water_scattering, water.scattering.into());
+            iface.set(&uni.subsurface_color, water.subsurface_color.into());
+            iface.set(&uni.subsurface_height, water.subsurface_height);
+            iface.set(&uni.shading_term, *shading_term as i32);
+
+            match geometry {
+                Geometry::Patches(tess) => {
+                    iface.set(&uni.camera_offset, [camera_pos.x, camera_pos.z]);
+                    iface.set(
+                        &uni.tessellation_scale,
+                        lod.scale(&projection, viewport[1]),
+                    );
+                    iface.set(&uni.max_tessellation_level, lod.max_level);
+
+                    let chunks =
+                        visible_patch_chunks(&camera.frustum(), &camera_pos);
+                    render_gate.render(&Default::default(), |mut tess_gate| {
+                        for chunk in chunks {
+                            tess_gate.render(tess.view(chunk)?)?;
+                        }
+                        Ok(())
+                    })
+                }
+                Geometry::Projected { tess, resolution } => {
+                    let inverse = glm::inverse(&view_projection);
+                    let [width, height] = *resolution;
+                    iface.set(&uni.inverse_view_projection, inverse.into());
+                    iface.set(&uni.grid_size, [width as i32, height as i32]);
+                    iface.set(&uni.overscan, PROJECTED_OVERSCAN);
+                    iface.set(&uni.max_distance, PROJECTED_MAX_DISTANCE);
+                    iface.set(
+                        &uni.displacement_fade,
+                        PROJECTED_DISPLACEMENT_FADE,
+                    );
+
+                    render_gate.render(&Default::default(), |mut tess_gate| {
+                        tess_gate.render(&*tess)
+                    })
+                }
+                Geometry::Dense(tess) => {
+                    iface.set(&uni.camera_offset, [camera_pos.x, camera_pos.z]);
+                    render_gate.render(&Default::default(), |mut tess_gate| {
+                        tess_gate.render(&*tess)
+                    })
+                }
             }
-            iface.set(&uni.exposure, exposure);
-
-            render_gate.render(&Default::default(), |mut tess_gate| {
-                iface.set(&uni.camera_offset, [camera_pos.x, camera_pos.z]);
-                tess_gate.render(&**tess)
-            })
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn patch_chunks_culled() {
+        let chunk_len = (PATCH_CHUNK_SIZE * PATCH_CHUNK_SIZE * 4) as usize;
+        let total = (PATCH_GRID_SIZE * PATCH_GRID_SIZE * 4) as usize;
+        let count = |ranges: &[Range<usize>]| {
+            ranges.iter().map(|range| range.len()).sum::<usize>()
+        };
+
+        // Looking down -z from above the water
+        let camera_pos = glm::vec3(100.0, 10.0, 100.0);
+        let projection = glm::infinite_perspective_rh_no(1.0, 1.1, 0.1);
+        let view = glm::translate(&glm::identity(), &-camera_pos);
+        let ranges = visible_patch_chunks(
+            &Frustum::from_matrix(&(projection * view)),
+            &camera_pos,
+        );
+
+        let visible = count(&ranges);
+        assert_eq!(visible % chunk_len, 0);
+        assert!(visible > 0);
+        // Everything behind the camera is gone
+        assert!(visible < total / 2, "{} of {}", visible, total);
+        // Merged ranges neither touch nor overlap
+        for pair in ranges.windows(2) {
+            assert!(pair[0].end < pair[1].start);
+        }
+
+        // Looking straight up, only the chunks under the camera remain
+        let view = glm::rotation(-std::f32::consts::FRAC_PI_2, &glm::Vec3::x())
+            * glm::translate(&glm::identity(), &-camera_pos);
+        let ranges = visible_patch_chunks(
+            &Frustum::from_matrix(&(projection * view)),
+            &camera_pos,
+        );
+        assert!(count(&ranges) <= 4 * chunk_len);
+    }
+}
@@ -0,0 +1,305 @@
+use crate::context::{BackBuffer, Context, OffscreenBuffer, Output};
+use crate::debug::capture::{label_framebuffer, label_texture};
+use crate::noise::BlueNoise;
+use luminance_derive::UniformInterface;
+use luminance_front::{
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pipeline::{Pipeline, PipelineGate, PipelineState, TextureBinding},
+    pixel::{Depth32F, Floating, RGBA32F},
+    shader::{Program, Uniform},
+    shading_gate::ShadingGate,
+    tess::{Mode, Tess},
+    texture::{Dim2, MagFilter, MinFilter, Sampler, Texture},
+};
+use std::any::Any;
+
+pub mod bloom;
+pub mod dither;
+pub mod fxaa;
+pub mod tonemap;
+
+pub const QUAD_VS_SRC: crate::shader::ShaderSource =
+    crate::shader_source!("./shaders/quad.vert");
+
+/// The scene is rendered into this, and only reaches the back buffer through
+/// the post-processing passes.
+pub type HdrFramebuffer = Framebuffer<Dim2, RGBA32F, Depth32F>;
+
+/// Output of a pass that is read by the next one.
+pub type PassFramebuffer = Framebuffer<Dim2, RGBA32F, ()>;
+
+pub type PassTexture = Texture<Dim2, RGBA32F>;
+
+fn sampler() -> Sampler {
+    Sampler {
+        mag_filter: MagFilter::Linear,
+        min_filter: MinFilter::Linear,
+        ..Default::default()
+    }
+}
+
+pub fn quad(context: &mut Context) -> anyhow::Result<Tess<()>> {
+    Ok(context
+        .new_tess()
+        .set_mode(Mode::TriangleStrip)
+        .set_vertex_nb(4)
+        .build()?)
+}
+
+/// What a pass renders into. The last enabled pass writes to the back
+/// buffer, which gets sRGB encoding on write, or to an offscreen buffer.
+pub enum Target<'a> {
+    Buffer(&'a mut PassFramebuffer),
+    BackBuffer(&'a BackBuffer),
+    Offscreen(&'a OffscreenBuffer),
+}
+
+impl<'a> From<Output<'a>> for Target<'a> {
+    fn from(output: Output<'a>) -> Self {
+        match output {
+            Output::BackBuffer(back_buffer) => Self::BackBuffer(back_buffer),
+            Output::Offscreen(buffer) => Self::Offscreen(buffer),
+        }
+    }
+}
+
+impl Target<'_> {
+    pub fn pipeline<F>(
+        self,
+        pipeline_gate: &mut PipelineGate,
+        f: F,
+    ) -> anyhow::Result<()>
+    where
+        F: for<'a> FnOnce(Pipeline<'a>, ShadingGate<'a>) -> anyhow::Result<()>,
+    {
+        match self {
+            Self::Buffer(framebuffer) => pipeline_gate
+                .pipeline(&*framebuffer, &PipelineState::new(), f)
+                .into_result(),
+            Self::BackBuffer(back_buffer) => pipeline_gate
+                .pipeline(
+                    back_buffer,
+                    &PipelineState::new().enable_srgb(true),
+                    f,
+                )
+                .into_result(),
+            Self::Offscreen(buffer) => pipeline_gate
+                .pipeline(buffer, &PipelineState::new().enable_srgb(true), f)
+                .into_result(),
+        }
+    }
+}
+
+/// Per-frame state and resources shared by the passes.
+pub struct Frame<'a> {
+    /// Seconds since start.
+    pub time: f32,
+    /// Number of frames rendered before this one.
+    pub index: u64,
+    pub blue_noise: &'a mut BlueNoise,
+}
+
+/// A fullscreen pass reading the output of the previous one.
+pub trait Pass {
+    fn name(&self) -> &'static str;
+
+    /// Called when the window, and with it every buffer in the chain,
+    /// changes size.
+    fn resize(
+        &mut self,
+        _context: &mut Context,
+        _size: [u32; 2],
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        input: &mut PassTexture,
+        output: Target,
+        frame: &mut Frame,
+    ) -> anyhow::Result<()>;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+struct Entry {
+    enabled: bool,
+    pass: Box<dyn Pass>,
+}
+
+#[derive(UniformInterface)]
+struct BlitInterface {
+    input_texture: Uniform<TextureBinding<Dim2, Floating>>,
+}
+
+fn new_buffers(
+    context: &mut Context,
+    size: [u32; 2],
+) -> anyhow::Result<(HdrFramebuffer, [PassFramebuffer; 2])> {
+    let mut scene_buffer: HdrFramebuffer =
+        context.new_framebuffer(size, 0, sampler())?;
+    let mut buffers: [PassFramebuffer; 2] = [
+        context.new_framebuffer(size, 0, sampler())?,
+        context.new_framebuffer(size, 0, sampler())?,
+    ];
+
+    label_framebuffer(context, &scene_buffer, "scene")?;
+    label_texture(context, scene_buffer.color_slot(), "scene color")?;
+    label_texture(context, scene_buffer.depth_stencil_slot(), "scene depth")?;
+    for (buffer, parity) in buffers.iter_mut().zip(&["even", "odd"]) {
+        let label = format!("post-process {}", parity);
+        label_framebuffer(context, buffer, &label)?;
+        label_texture(context, buffer.color_slot(), &label)?;
+    }
+
+    Ok((scene_buffer, buffers))
+}
+
+/// Scene buffer and the ordered list of passes that take it to the back
+/// buffer.
+pub struct PostProcess {
+    scene_buffer: HdrFramebuffer,
+    buffers: [PassFramebuffer; 2],
+    passes: Vec<Entry>,
+    blit_shader: Program<(), (), BlitInterface>,
+    quad: Tess<()>,
+    frame_index: u64,
+}
+
+impl PostProcess {
+    pub fn new(context: &mut Context, size: [u32; 2]) -> anyhow::Result<Self> {
+        let blit_shader = crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/blit.frag"),
+        )?;
+
+        let (scene_buffer, buffers) = new_buffers(context, size)?;
+        Ok(Self {
+            scene_buffer,
+            buffers,
+            passes: Vec::new(),
+            blit_shader,
+            quad: quad(context)?,
+            frame_index: 0,
+        })
+    }
+
+    /// Add a pass to the end of the chain, enabled.
+    pub fn push(&mut self, pass: impl Pass + 'static) {
+        self.passes.push(Entry {
+            enabled: true,
+            pass: Box::new(pass),
+        });
+    }
+
+    /// Enable or disable the pass at `index`, returning its name and new
+    /// state.
+    pub fn toggle(&mut self, index: usize) -> Option<(&'static str, bool)> {
+        let entry = self.passes.get_mut(index)?;
+        entry.enabled = !entry.enabled;
+        Some((entry.pass.name(), entry.enabled))
+    }
+
+    /// The first pass of type `T` in the chain.
+    pub fn pass_mut<T: Pass + 'static>(&mut self) -> Option<&mut T> {
+        self.passes
+            .iter_mut()
+            .find_map(|entry| entry.pass.as_any_mut().downcast_mut())
+    }
+
+    pub fn scene_buffer(&self) -> &HdrFramebuffer {
+        &self.scene_buffer
+    }
+
+    pub fn resize(
+        &mut self,
+        context: &mut Context,
+        size: [u32; 2],
+    ) -> anyhow::Result<()> {
+        let (scene_buffer, buffers) = new_buffers(context, size)?;
+        self.scene_buffer = scene_buffer;
+        self.buffers = buffers;
+
+        for entry in self.passes.iter_mut() {
+            entry.pass.resize(context, size)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run the enabled passes in order, from the scene buffer to `output`.
+    pub fn render(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        output: Output,
+        blue_noise: &mut BlueNoise,
+        time: f32,
+    ) -> anyhow::Result<()> {
+        let Self {
+            scene_buffer,
+            buffers: [even, odd],
+            passes,
+            blit_shader,
+            quad,
+            frame_index,
+        } = self;
+
+        let mut frame = Frame {
+            time,
+            index: *frame_index,
+            blue_noise,
+        };
+        *frame_index += 1;
+
+        let mut enabled = passes
+            .iter_mut()
+            .filter(|entry| entry.enabled)
+            .map(|entry| &mut entry.pass)
+            .enumerate()
+            .peekable();
+
+        if enabled.peek().is_none() {
+            let input = scene_buffer.color_slot();
+            return Target::from(output).pipeline(
+                pipeline_gate,
+                |pipeline, mut shader_gate| {
+                    let input = pipeline.bind_texture(input)?;
+                    shader_gate.shade(
+                        blit_shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface.set(&uni.input_texture, input.binding());
+                            render_gate
+                                .render(&Default::default(), |mut tess_gate| {
+                                    tess_gate.render(&*quad)
+                                })
+                        },
+                    )
+                },
+            );
+        }
+
+        while let Some((n, pass)) = enabled.next() {
+            let (input, buffer) = match n {
+                0 => (scene_buffer.color_slot(), &mut *even),
+                n if n % 2 == 1 => (even.color_slot(), &mut *odd),
+                _ => (odd.color_slot(), &mut *even),
+            };
+
+            let target = match enabled.peek() {
+                Some(_) => Target::Buffer(buffer),
+                None => output.into(),
+            };
+
+            let _scope = crate::profiler::scope(pass.name());
+            pass.render(pipeline_gate, input, target, &mut frame)?;
+        }
+
+        Ok(())
+    }
+}
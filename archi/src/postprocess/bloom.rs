@@ -0,0 +1,289 @@
+use super::{Frame, Pass, PassFramebuffer, PassTexture, Target, QUAD_VS_SRC};
+use crate::context::Context;
+use luminance_derive::UniformInterface;
+use luminance_front::{
+    context::GraphicsContext,
+    pipeline::{PipelineGate, TextureBinding},
+    pixel::Floating,
+    shader::{Program, Uniform},
+    tess::Tess,
+    texture::Dim2,
+};
+use std::any::Any;
+
+/// Most levels in the chain, the first being half the size of the scene.
+const MAX_LEVELS: usize = 6;
+
+#[derive(UniformInterface)]
+struct DownsampleInterface {
+    input_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    karis_average: Uniform<i32>,
+}
+
+#[derive(UniformInterface)]
+struct UpsampleInterface {
+    coarse_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    fine_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    radius: Uniform<f32>,
+}
+
+#[derive(UniformInterface)]
+struct CompositeInterface {
+    input_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    bloom_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    levels: Uniform<i32>,
+    intensity: Uniform<f32>,
+}
+
+/// Sizes of the downsampled levels, halving from half the scene size.
+fn level_sizes(size: [u32; 2]) -> Vec<[u32; 2]> {
+    let mut sizes = Vec::with_capacity(MAX_LEVELS);
+    let [mut width, mut height] = size;
+    while sizes.len() < MAX_LEVELS && width > 1 && height > 1 {
+        width /= 2;
+        height /= 2;
+        sizes.push([width, height]);
+    }
+    sizes
+}
+
+/// Bloom without a threshold: the scene is blurred at increasing radii and
+/// blended in, which moves energy around without adding any, or removing any
+/// unless `karis_average` is set. Belongs before the tonemapper, so that it
+/// works on scene luminance.
+pub struct Bloom {
+    /// Fraction of the scene that is replaced by its blurred copy.
+    pub intensity: f32,
+    /// Radius of the upsampling filter, in texels of the smaller level.
+    pub radius: f32,
+    /// Weight the first downsample by inverse luminance, which keeps small
+    /// very bright highlights such as sun glints from flickering, at the cost
+    /// of removing some of their energy from the bloom. Off by default.
+    pub karis_average: bool,
+    /// Each level of the scene, downsampled from the one before.
+    down: Vec<PassFramebuffer>,
+    /// Each level with every smaller level upsampled and added to it. The
+    /// smallest level has nothing to add, so it has no counterpart here.
+    up: Vec<PassFramebuffer>,
+    downsample_shader: Program<(), (), DownsampleInterface>,
+    upsample_shader: Program<(), (), UpsampleInterface>,
+    composite_shader: Program<(), (), CompositeInterface>,
+    quad: Tess<()>,
+}
+
+impl Bloom {
+    pub fn new(context: &mut Context, size: [u32; 2]) -> anyhow::Result<Self> {
+        let downsample_shader = crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/bloom_downsample.frag"),
+        )?;
+
+        let upsample_shader = crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/bloom_upsample.frag"),
+        )?;
+
+        let composite_shader = crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/bloom_composite.frag"),
+        )?;
+
+        let mut bloom = Self {
+            intensity: 0.04,
+            radius: 1.0,
+            karis_average: false,
+            down: Vec::new(),
+            up: Vec::new(),
+            downsample_shader,
+            upsample_shader,
+            composite_shader,
+            quad: super::quad(context)?,
+        };
+        bloom.resize(context, size)?;
+
+        Ok(bloom)
+    }
+
+    fn downsample(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        input: &mut PassTexture,
+    ) -> anyhow::Result<()> {
+        let Self {
+            karis_average,
+            down,
+            downsample_shader,
+            quad,
+            ..
+        } = self;
+
+        for level in 0..down.len() {
+            let (smaller, rest) = down.split_at_mut(level);
+            let input = match smaller.last_mut() {
+                Some(previous) => previous.color_slot(),
+                None => &mut *input,
+            };
+
+            // Only the first level sees individual bright pixels
+            let karis_average = *karis_average && level == 0;
+
+            Target::Buffer(&mut rest[0]).pipeline(
+                pipeline_gate,
+                |pipeline, mut shader_gate| {
+                    let input = pipeline.bind_texture(input)?;
+                    shader_gate.shade(
+                        downsample_shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface.set(&uni.input_texture, input.binding());
+                            iface.set(&uni.karis_average, karis_average as i32);
+                            render_gate
+                                .render(&Default::default(), |mut tess_gate| {
+                                    tess_gate.render(&*quad)
+                                })
+                        },
+                    )
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn upsample(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+    ) -> anyhow::Result<()> {
+        let Self {
+            radius,
+            down,
+            up,
+            upsample_shader,
+            quad,
+            ..
+        } = self;
+
+        for level in (0..up.len()).rev() {
+            let (fine, coarse) = down.split_at_mut(level + 1);
+            let fine = fine[level].color_slot();
+            let (output, accumulated) = up.split_at_mut(level + 1);
+            let coarse = match accumulated.first_mut() {
+                Some(accumulated) => accumulated.color_slot(),
+                None => coarse[0].color_slot(),
+            };
+
+            Target::Buffer(&mut output[level]).pipeline(
+                pipeline_gate,
+                |pipeline, mut shader_gate| {
+                    let coarse = pipeline.bind_texture(coarse)?;
+                    let fine = pipeline.bind_texture(fine)?;
+                    shader_gate.shade(
+                        upsample_shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface.set(&uni.coarse_texture, coarse.binding());
+                            iface.set(&uni.fine_texture, fine.binding());
+                            iface.set(&uni.radius, *radius);
+                            render_gate
+                                .render(&Default::default(), |mut tess_gate| {
+                                    tess_gate.render(&*quad)
+                                })
+                        },
+                    )
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Pass for Bloom {
+    fn name(&self) -> &'static str {
+        "bloom"
+    }
+
+    fn resize(
+        &mut self,
+        context: &mut Context,
+        size: [u32; 2],
+    ) -> anyhow::Result<()> {
+        let sizes = level_sizes(size);
+
+        self.down = sizes
+            .iter()
+            .map(|&size| context.new_framebuffer(size, 0, super::sampler()))
+            .collect::<Result<_, _>>()?;
+        self.up = sizes
+            .iter()
+            .take(sizes.len().saturating_sub(1))
+            .map(|&size| context.new_framebuffer(size, 0, super::sampler()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        input: &mut PassTexture,
+        output: Target,
+        _frame: &mut Frame,
+    ) -> anyhow::Result<()> {
+        self.downsample(pipeline_gate, input)?;
+        self.upsample(pipeline_gate)?;
+
+        let Self {
+            intensity,
+            down,
+            up,
+            composite_shader,
+            quad,
+            ..
+        } = self;
+
+        let levels = down.len();
+        let bloom = match (up.first_mut(), down.first_mut()) {
+            (Some(accumulated), _) => Some(accumulated.color_slot()),
+            (None, Some(smallest)) => Some(smallest.color_slot()),
+            (None, None) => None,
+        };
+
+        output.pipeline(pipeline_gate, |pipeline, mut shader_gate| {
+            let input = pipeline.bind_texture(input)?;
+            let bloom = match bloom {
+                Some(bloom) => Some(pipeline.bind_texture(bloom)?),
+                None => None,
+            };
+            shader_gate.shade(
+                composite_shader,
+                |mut iface, uni, mut render_gate| {
+                    iface.set(&uni.input_texture, input.binding());
+                    iface.set(&uni.levels, levels as i32);
+                    match &bloom {
+                        Some(bloom) => {
+                            iface.set(&uni.bloom_texture, bloom.binding());
+                            iface.set(&uni.intensity, *intensity);
+                        }
+                        // Too small a window to have any levels
+                        None => iface.set(&uni.intensity, 0.0),
+                    }
+                    render_gate.render(&Default::default(), |mut tess_gate| {
+                        tess_gate.render(&*quad)
+                    })
+                },
+            )
+        })
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
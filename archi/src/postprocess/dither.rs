@@ -0,0 +1,89 @@
+use super::{Frame, Pass, PassTexture, Target, QUAD_VS_SRC};
+use crate::context::Context;
+use crate::noise::BlueNoise;
+use luminance_derive::UniformInterface;
+use luminance_front::{
+    pipeline::{PipelineGate, TextureBinding},
+    pixel::Floating,
+    shader::{Program, Uniform},
+    tess::Tess,
+    texture::Dim2,
+};
+use std::any::Any;
+
+#[derive(UniformInterface)]
+struct DitherInterface {
+    input_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    blue_noise_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    blue_noise_frame: Uniform<i32>,
+    levels: Uniform<f32>,
+}
+
+/// Adds blue noise of about one quantisation step before the output is
+/// quantised, to break up banding in smooth gradients. Belongs last in the
+/// chain.
+pub struct Dither {
+    /// Quantisation levels of the back buffer per channel.
+    pub levels: f32,
+    shader: Program<(), (), DitherInterface>,
+    quad: Tess<()>,
+}
+
+impl Dither {
+    pub fn new(context: &mut Context) -> anyhow::Result<Self> {
+        let shader = crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/dither.frag"),
+        )?;
+
+        Ok(Self {
+            levels: 255.0,
+            shader,
+            quad: super::quad(context)?,
+        })
+    }
+}
+
+impl Pass for Dither {
+    fn name(&self) -> &'static str {
+        "dither"
+    }
+
+    fn render(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        input: &mut PassTexture,
+        output: Target,
+        frame: &mut Frame,
+    ) -> anyhow::Result<()> {
+        let Self {
+            levels,
+            shader,
+            quad,
+        } = self;
+
+        let blue_noise_frame = BlueNoise::frame(frame.index);
+        let blue_noise = &mut frame.blue_noise.texture;
+
+        output.pipeline(pipeline_gate, |pipeline, mut shader_gate| {
+            let input = pipeline.bind_texture(input)?;
+            let blue_noise = pipeline.bind_texture(blue_noise)?;
+            shader_gate.shade(shader, |mut iface, uni, mut render_gate| {
+                iface.set(&uni.input_texture, input.binding());
+                iface.set(&uni.blue_noise_texture, blue_noise.binding());
+                iface.set(&uni.blue_noise_frame, blue_noise_frame);
+                iface.set(&uni.levels, *levels);
+                render_gate.render(&Default::default(), |mut tess_gate| {
+                    tess_gate.render(&*quad)
+                })
+            })
+        })
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
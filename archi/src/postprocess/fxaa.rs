@@ -0,0 +1,89 @@
+use super::{Frame, Pass, PassTexture, Target, QUAD_VS_SRC};
+use crate::context::Context;
+use luminance_derive::UniformInterface;
+use luminance_front::{
+    pipeline::{PipelineGate, TextureBinding},
+    pixel::Floating,
+    shader::{Program, Uniform},
+    tess::Tess,
+    texture::Dim2,
+};
+use std::any::Any;
+
+#[derive(UniformInterface)]
+struct FxaaInterface {
+    input_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    span_max: Uniform<f32>,
+    reduce_min: Uniform<f32>,
+    reduce_mul: Uniform<f32>,
+}
+
+/// Fast approximate antialiasing. Runs on tonemapped colour, so it belongs
+/// after the tonemapper.
+pub struct Fxaa {
+    /// Longest blur along an edge, in pixels.
+    pub span_max: f32,
+    pub reduce_min: f32,
+    pub reduce_mul: f32,
+    shader: Program<(), (), FxaaInterface>,
+    quad: Tess<()>,
+}
+
+impl Fxaa {
+    pub fn new(context: &mut Context) -> anyhow::Result<Self> {
+        let shader = crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/fxaa.frag"),
+        )?;
+
+        Ok(Self {
+            span_max: 8.0,
+            reduce_min: 1.0 / 128.0,
+            reduce_mul: 1.0 / 8.0,
+            shader,
+            quad: super::quad(context)?,
+        })
+    }
+}
+
+impl Pass for Fxaa {
+    fn name(&self) -> &'static str {
+        "fxaa"
+    }
+
+    fn render(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        input: &mut PassTexture,
+        output: Target,
+        _frame: &mut Frame,
+    ) -> anyhow::Result<()> {
+        let Self {
+            span_max,
+            reduce_min,
+            reduce_mul,
+            shader,
+            quad,
+        } = self;
+
+        output.pipeline(pipeline_gate, |pipeline, mut shader_gate| {
+            let input = pipeline.bind_texture(input)?;
+            shader_gate.shade(shader, |mut iface, uni, mut render_gate| {
+                iface.set(&uni.input_texture, input.binding());
+                iface.set(&uni.span_max, *span_max);
+                iface.set(&uni.reduce_min, *reduce_min);
+                iface.set(&uni.reduce_mul, *reduce_mul);
+                render_gate.render(&Default::default(), |mut tess_gate| {
+                    tess_gate.render(&*quad)
+                })
+            })
+        })
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
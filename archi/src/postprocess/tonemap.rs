@@ -0,0 +1,353 @@
+use super::{Frame, Pass, PassTexture, Target, QUAD_VS_SRC};
+use crate::context::Context;
+use luminance::blending::{Blending, Equation, Factor};
+use luminance_derive::UniformInterface;
+use luminance_front::{
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pipeline::{PipelineGate, PipelineState, TextureBinding},
+    pixel::{Floating, RGBA32F},
+    render_state::RenderState,
+    shader::{Program, Uniform},
+    tess::{Mode, Tess},
+    texture::{Dim2, MagFilter, MinFilter, Sampler},
+};
+use std::any::Any;
+
+/// Number of bins in the luminance histogram.
+const BINS: u32 = 64;
+
+/// The histogram is built from this many samples along each axis of the
+/// scene.
+const GRID_SIZE: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Exponential = 0,
+    Reinhard = 1,
+    Aces = 2,
+    Agx = 3,
+}
+
+impl Operator {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Exponential => Self::Reinhard,
+            Self::Reinhard => Self::Aces,
+            Self::Aces => Self::Agx,
+            Self::Agx => Self::Exponential,
+        }
+    }
+}
+
+impl Default for Operator {
+    fn default() -> Self {
+        Self::Aces
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AutoExposure {
+    pub enabled: bool,
+    /// How quickly exposure catches up with the scene, per second.
+    pub adaptation_speed: f32,
+    /// Range of log2 luminance covered by the histogram.
+    pub min_log_luminance: f32,
+    pub max_log_luminance: f32,
+    /// Fractions of the histogram below and above which samples are left
+    /// out of the average, so that the sun or deep shadows alone do not
+    /// swing the exposure.
+    pub low_percentile: f32,
+    pub high_percentile: f32,
+    /// Luminance that the average of the scene is mapped to.
+    pub key: f32,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            adaptation_speed: 1.5,
+            min_log_luminance: -10.0,
+            max_log_luminance: 10.0,
+            low_percentile: 0.5,
+            high_percentile: 0.95,
+            key: 0.18,
+        }
+    }
+}
+
+#[derive(UniformInterface)]
+struct HistogramInterface {
+    hdr_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    grid_size: Uniform<i32>,
+    bins: Uniform<i32>,
+    min_log_luminance: Uniform<f32>,
+    max_log_luminance: Uniform<f32>,
+}
+
+#[derive(UniformInterface)]
+struct ExposureInterface {
+    histogram: Uniform<TextureBinding<Dim2, Floating>>,
+    previous_exposure: Uniform<TextureBinding<Dim2, Floating>>,
+    min_log_luminance: Uniform<f32>,
+    max_log_luminance: Uniform<f32>,
+    low_percentile: Uniform<f32>,
+    high_percentile: Uniform<f32>,
+    key: Uniform<f32>,
+    adaptation: Uniform<f32>,
+}
+
+#[derive(UniformInterface)]
+struct TonemapInterface {
+    hdr_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    exposure_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    auto_exposure: Uniform<i32>,
+    exposure: Uniform<f32>,
+    tonemap_operator: Uniform<i32>,
+}
+
+type DataFramebuffer = Framebuffer<Dim2, RGBA32F, ()>;
+
+pub struct Tonemapper {
+    pub operator: Operator,
+    pub auto_exposure: AutoExposure,
+    /// Exposure multiplier, applied on top of the automatic exposure when
+    /// that is enabled.
+    pub exposure: f32,
+    histogram: DataFramebuffer,
+    /// Exposure of the previous and the current frame, in a single texel.
+    exposure_buffers: [DataFramebuffer; 2],
+    histogram_shader: Program<(), (), HistogramInterface>,
+    exposure_shader: Program<(), (), ExposureInterface>,
+    tonemap_shader: Program<(), (), TonemapInterface>,
+    points: Tess<()>,
+    quad: Tess<()>,
+    last_time: Option<f32>,
+}
+
+impl Tonemapper {
+    pub fn new(context: &mut Context) -> anyhow::Result<Self> {
+        let sampler = Sampler {
+            mag_filter: MagFilter::Nearest,
+            min_filter: MinFilter::Nearest,
+            ..Default::default()
+        };
+
+        let histogram = context.new_framebuffer([BINS, 1], 0, sampler)?;
+        let exposure_buffers = [
+            context.new_framebuffer([1, 1], 0, sampler)?,
+            context.new_framebuffer([1, 1], 0, sampler)?,
+        ];
+
+        let histogram_shader = crate::shader::from_sources(
+            context,
+            None,
+            crate::shader_source!("./shaders/histogram.vert"),
+            None,
+            crate::shader_source!("./shaders/histogram.frag"),
+        )?;
+
+        let exposure_shader = crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/exposure.frag"),
+        )?;
+
+        let tonemap_shader = crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/tonemap.frag"),
+        )?;
+
+        let points = context
+            .new_tess()
+            .set_mode(Mode::Point)
+            .set_vertex_nb((GRID_SIZE * GRID_SIZE) as usize)
+            .build()?;
+
+        let quad = super::quad(context)?;
+
+        Ok(Self {
+            operator: Default::default(),
+            auto_exposure: Default::default(),
+            exposure: 1.0,
+            histogram,
+            exposure_buffers,
+            histogram_shader,
+            exposure_shader,
+            tonemap_shader,
+            points,
+            quad,
+            last_time: None,
+        })
+    }
+
+    /// Histogram the luminance of the scene and move the exposure towards
+    /// the one that suits it.
+    fn measure_exposure(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        input: &mut PassTexture,
+        delta_t: f32,
+    ) -> anyhow::Result<()> {
+        self.exposure_buffers.swap(0, 1);
+
+        let Self {
+            auto_exposure,
+            histogram,
+            exposure_buffers: [previous_exposure, current_exposure],
+            histogram_shader,
+            exposure_shader,
+            points,
+            quad,
+            ..
+        } = self;
+
+        let settings = *auto_exposure;
+
+        pipeline_gate
+            .pipeline(
+                &*histogram,
+                &PipelineState::new().set_clear_color([0.0, 0.0, 0.0, 0.0]),
+                |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                    let input = pipeline.bind_texture(input)?;
+                    shader_gate.shade(
+                        histogram_shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface.set(&uni.hdr_texture, input.binding());
+                            iface.set(&uni.grid_size, GRID_SIZE as i32);
+                            iface.set(&uni.bins, BINS as i32);
+                            iface.set(
+                                &uni.min_log_luminance,
+                                settings.min_log_luminance,
+                            );
+                            iface.set(
+                                &uni.max_log_luminance,
+                                settings.max_log_luminance,
+                            );
+
+                            let state =
+                                RenderState::default().set_blending(Blending {
+                                    equation: Equation::Additive,
+                                    src: Factor::One,
+                                    dst: Factor::One,
+                                });
+                            render_gate.render(&state, |mut tess_gate| {
+                                tess_gate.render(&*points)
+                            })
+                        },
+                    )
+                },
+            )
+            .into_result()?;
+
+        let adaptation = 1.0 - (-delta_t * settings.adaptation_speed).exp();
+
+        pipeline_gate
+            .pipeline(
+                &*current_exposure,
+                &Default::default(),
+                |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                    let histogram =
+                        pipeline.bind_texture(histogram.color_slot())?;
+                    let previous_exposure = pipeline
+                        .bind_texture(previous_exposure.color_slot())?;
+                    shader_gate.shade(
+                        exposure_shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface.set(&uni.histogram, histogram.binding());
+                            iface.set(
+                                &uni.previous_exposure,
+                                previous_exposure.binding(),
+                            );
+                            iface.set(
+                                &uni.min_log_luminance,
+                                settings.min_log_luminance,
+                            );
+                            iface.set(
+                                &uni.max_log_luminance,
+                                settings.max_log_luminance,
+                            );
+                            iface.set(
+                                &uni.low_percentile,
+                                settings.low_percentile,
+                            );
+                            iface.set(
+                                &uni.high_percentile,
+                                settings.high_percentile,
+                            );
+                            iface.set(&uni.key, settings.key);
+                            iface.set(&uni.adaptation, adaptation);
+                            render_gate
+                                .render(&Default::default(), |mut tess_gate| {
+                                    tess_gate.render(&*quad)
+                                })
+                        },
+                    )
+                },
+            )
+            .into_result()?;
+
+        Ok(())
+    }
+}
+
+impl Pass for Tonemapper {
+    fn name(&self) -> &'static str {
+        "tonemap"
+    }
+
+    fn render(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        input: &mut PassTexture,
+        output: Target,
+        frame: &mut Frame,
+    ) -> anyhow::Result<()> {
+        let delta_t = frame.time - self.last_time.unwrap_or(frame.time);
+        self.last_time = Some(frame.time);
+
+        if self.auto_exposure.enabled {
+            self.measure_exposure(pipeline_gate, input, delta_t)?;
+        }
+
+        let Self {
+            operator,
+            auto_exposure,
+            exposure,
+            exposure_buffers: [_, current_exposure],
+            tonemap_shader,
+            quad,
+            ..
+        } = self;
+
+        output.pipeline(pipeline_gate, |pipeline, mut shader_gate| {
+            let input = pipeline.bind_texture(input)?;
+            let current_exposure =
+                pipeline.bind_texture(current_exposure.color_slot())?;
+            shader_gate.shade(
+                tonemap_shader,
+                |mut iface, uni, mut render_gate| {
+                    iface.set(&uni.hdr_texture, input.binding());
+                    iface
+                        .set(&uni.exposure_texture, current_exposure.binding());
+                    iface.set(&uni.auto_exposure, auto_exposure.enabled as i32);
+                    iface.set(&uni.exposure, *exposure);
+                    iface.set(&uni.tonemap_operator, *operator as i32);
+                    render_gate.render(&Default::default(), |mut tess_gate| {
+                        tess_gate.render(&*quad)
+                    })
+                },
+            )
+        })
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,428 @@
+//! Named scopes timed on the CPU and on the GPU.
+//!
+//! `let _scope = profiler::scope("hkt");` times the rest of the block. GPU
+//! times come from timer queries, which are read a few frames later once the
+//! GPU has caught up, so profiling doesn't stall the pipeline. Averages over
+//! the last frames are logged and drawn by the overlay and, if
+//! `ARCHI_PROFILE_CSV` names a file, every frame goes there.
+
+use crate::debug::DebugGroup;
+use gl::types::{GLint, GLuint, GLuint64};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+mod overlay;
+
+pub use overlay::Overlay;
+
+/// Frames that averages are taken over.
+const WINDOW: usize = 60;
+
+/// Frames whose queries may be waiting for the GPU before reading them
+/// blocks.
+const MAX_FRAMES_IN_FLIGHT: usize = 8;
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+thread_local! {
+    static PROFILER: RefCell<Option<Profiler>> = RefCell::new(None);
+}
+
+fn with<T>(f: impl FnOnce(&mut Profiler) -> T) -> Option<T> {
+    PROFILER.with(|profiler| profiler.borrow_mut().as_mut().map(f))
+}
+
+/// Start profiling, or stop and drop what has been measured so far.
+pub fn set_enabled(enabled: bool) -> anyhow::Result<()> {
+    let profiler = match enabled {
+        true if is_enabled() => return Ok(()),
+        true => Some(Profiler::new()?),
+        false => None,
+    };
+    PROFILER.with(|current| *current.borrow_mut() = profiler);
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    with(|_| ()).is_some()
+}
+
+/// Times from here until the returned guard is dropped. Scopes nest, and
+/// must end in the frame they started in. Whether profiling or not, they are
+/// also debug groups, so frame captures have the same structure.
+pub fn scope(name: &'static str) -> Scope {
+    let group = DebugGroup::new(name);
+    Scope {
+        active: with(|profiler| profiler.begin(name)).is_some(),
+        _group: group,
+    }
+}
+
+/// Marks the end of a frame, after which the scopes of earlier frames are
+/// read back when the GPU is done with them.
+pub fn end_frame() -> anyhow::Result<()> {
+    with(Profiler::end_frame).transpose()?;
+    Ok(())
+}
+
+/// Averages of every scope seen over the last frames, in the order they
+/// were first seen.
+pub fn averages() -> Vec<Average> {
+    with(|profiler| profiler.stats.iter().map(Stat::average).collect())
+        .unwrap_or_default()
+}
+
+/// Averages of every scope seen over every frame since profiling started.
+pub fn overall_averages() -> Vec<Average> {
+    with(|profiler| profiler.stats.iter().map(Stat::overall_average).collect())
+        .unwrap_or_default()
+}
+
+#[must_use]
+pub struct Scope {
+    active: bool,
+    _group: DebugGroup,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if self.active {
+            with(Profiler::end);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Average {
+    pub name: &'static str,
+    /// Number of scopes this one was nested in.
+    pub depth: usize,
+    pub cpu_ms: f32,
+    pub gpu_ms: f32,
+}
+
+/// Times of a scope over the last `WINDOW` frames it was in, and summed
+/// over all of them.
+struct Stat {
+    name: &'static str,
+    depth: usize,
+    samples: VecDeque<(f32, f32)>,
+    total: (f64, f64),
+    frames: u64,
+}
+
+impl Stat {
+    fn new(name: &'static str, depth: usize) -> Self {
+        Self {
+            name,
+            depth,
+            samples: VecDeque::with_capacity(WINDOW),
+            total: (0.0, 0.0),
+            frames: 0,
+        }
+    }
+
+    fn push(&mut self, cpu_ms: f32, gpu_ms: f32) {
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((cpu_ms, gpu_ms));
+
+        self.total.0 += cpu_ms as f64;
+        self.total.1 += gpu_ms as f64;
+        self.frames += 1;
+    }
+
+    fn overall_average(&self) -> Average {
+        let frames = self.frames.max(1) as f64;
+        Average {
+            name: self.name,
+            depth: self.depth,
+            cpu_ms: (self.total.0 / frames) as f32,
+            gpu_ms: (self.total.1 / frames) as f32,
+        }
+    }
+
+    fn average(&self) -> Average {
+        let count = self.samples.len().max(1) as f32;
+        let (cpu, gpu) = self
+            .samples
+            .iter()
+            .fold((0.0, 0.0), |(a, b), (cpu, gpu)| (a + cpu, b + gpu));
+        Average {
+            name: self.name,
+            depth: self.depth,
+            cpu_ms: cpu / count,
+            gpu_ms: gpu / count,
+        }
+    }
+}
+
+/// A scope of a frame. The GPU time is between two timestamps rather than
+/// from a `GL_TIME_ELAPSED` query, since those can't be nested.
+struct Record {
+    name: &'static str,
+    depth: usize,
+    cpu: Duration,
+    timestamps: [GLuint; 2],
+}
+
+/// Sum of the times of each scope in `records`, which can be in a frame more
+/// than once, in the order of their first appearance.
+fn frame_totals(
+    records: &[(&'static str, usize, f32, f32)],
+) -> Vec<(&'static str, usize, f32, f32)> {
+    let mut totals: Vec<(&'static str, usize, f32, f32)> = Vec::new();
+    for &(name, depth, cpu, gpu) in records {
+        match totals.iter_mut().find(|total| total.0 == name) {
+            Some(total) => {
+                total.2 += cpu;
+                total.3 += gpu;
+            }
+            None => totals.push((name, depth, cpu, gpu)),
+        }
+    }
+    totals
+}
+
+struct Profiler {
+    frame_index: u64,
+    records: Vec<Record>,
+    /// Scopes that have begun and not ended, as indices into `records`.
+    open: Vec<(usize, Instant)>,
+    in_flight: VecDeque<(u64, Vec<Record>)>,
+    free_queries: Vec<GLuint>,
+    stats: Vec<Stat>,
+    csv: Option<std::io::BufWriter<std::fs::File>>,
+    last_report: Instant,
+}
+
+impl Profiler {
+    fn new() -> anyhow::Result<Self> {
+        use anyhow::Context as _;
+
+        let csv = match std::env::var_os("ARCHI_PROFILE_CSV") {
+            Some(path) => {
+                let file = std::fs::File::create(&path).with_context(|| {
+                    format!("failed to create {}", path.to_string_lossy())
+                })?;
+                let mut csv = std::io::BufWriter::new(file);
+                writeln!(csv, "frame,scope,cpu_ms,gpu_ms")?;
+                Some(csv)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            frame_index: 0,
+            records: Vec::new(),
+            open: Vec::new(),
+            in_flight: VecDeque::new(),
+            free_queries: Vec::new(),
+            stats: Vec::new(),
+            csv,
+            last_report: Instant::now(),
+        })
+    }
+
+    fn query(&mut self) -> GLuint {
+        self.free_queries.pop().unwrap_or_else(|| {
+            let mut query = 0;
+            unsafe { gl::GenQueries(1, &mut query) };
+            query
+        })
+    }
+
+    fn begin(&mut self, name: &'static str) {
+        let timestamps = [self.query(), self.query()];
+        unsafe { gl::QueryCounter(timestamps[0], gl::TIMESTAMP) };
+
+        self.open.push((self.records.len(), Instant::now()));
+        self.records.push(Record {
+            name,
+            depth: self.open.len() - 1,
+            cpu: Duration::default(),
+            timestamps,
+        });
+    }
+
+    fn end(&mut self) {
+        if let Some((index, start)) = self.open.pop() {
+            let record = &mut self.records[index];
+            record.cpu = start.elapsed();
+            unsafe { gl::QueryCounter(record.timestamps[1], gl::TIMESTAMP) };
+        }
+    }
+
+    fn end_frame(&mut self) -> anyhow::Result<()> {
+        // Scopes left open are dropped, along with those they contain.
+        if let Some(&(index, _)) = self.open.first() {
+            for record in self.records.drain(index..) {
+                self.free_queries.extend_from_slice(&record.timestamps);
+            }
+            self.open.clear();
+        }
+
+        let records = std::mem::take(&mut self.records);
+        self.in_flight.push_back((self.frame_index, records));
+        self.frame_index += 1;
+
+        while let Some((_, records)) = self.in_flight.front() {
+            let ready = records.iter().all(|record| {
+                let mut available: GLint = 0;
+                unsafe {
+                    gl::GetQueryObjectiv(
+                        record.timestamps[1],
+                        gl::QUERY_RESULT_AVAILABLE,
+                        &mut available,
+                    );
+                }
+                available != 0
+            });
+            if !ready && self.in_flight.len() <= MAX_FRAMES_IN_FLIGHT {
+                break;
+            }
+
+            let (frame, records) = self.in_flight.pop_front().unwrap();
+            self.collect(frame, records)?;
+        }
+
+        if self.last_report.elapsed() >= REPORT_INTERVAL {
+            self.last_report = Instant::now();
+            self.report();
+        }
+
+        Ok(())
+    }
+
+    /// Read the timestamps of a frame, which waits for them if they are not
+    /// available yet.
+    fn collect(
+        &mut self,
+        frame: u64,
+        records: Vec<Record>,
+    ) -> anyhow::Result<()> {
+        let times: Vec<_> = records
+            .iter()
+            .map(|record| {
+                let time = |query| {
+                    let mut time: GLuint64 = 0;
+                    unsafe {
+                        gl::GetQueryObjectui64v(
+                            query,
+                            gl::QUERY_RESULT,
+                            &mut time,
+                        );
+                    }
+                    time
+                };
+                let [begin, end] = record.timestamps;
+                let (begin, end) = (time(begin), time(end));
+                let gpu_ms = end.saturating_sub(begin) as f32 / 1e6;
+                let cpu_ms = record.cpu.as_secs_f32() * 1e3;
+                (record.name, record.depth, cpu_ms, gpu_ms)
+            })
+            .collect();
+
+        for record in records {
+            self.free_queries.extend_from_slice(&record.timestamps);
+        }
+
+        for (name, depth, cpu_ms, gpu_ms) in frame_totals(&times) {
+            if let Some(csv) = &mut self.csv {
+                writeln!(csv, "{},{},{},{}", frame, name, cpu_ms, gpu_ms)?;
+            }
+
+            match self.stats.iter_mut().find(|stat| stat.name == name) {
+                Some(stat) => stat.push(cpu_ms, gpu_ms),
+                None => {
+                    let mut stat = Stat::new(name, depth);
+                    stat.push(cpu_ms, gpu_ms);
+                    self.stats.push(stat);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn report(&self) {
+        let mut report = format!("average over {} frames, cpu / gpu:", WINDOW);
+        for stat in &self.stats {
+            let Average {
+                name,
+                depth,
+                cpu_ms,
+                gpu_ms,
+            } = stat.average();
+            let indent = 2 * (depth + 1);
+            report.push_str(&format!(
+                "\n{:indent$}{:width$} {:6.3}ms / {:6.3}ms",
+                "",
+                name,
+                cpu_ms,
+                gpu_ms,
+                indent = indent,
+                width = 24 - indent,
+            ));
+        }
+        tracing::info!("{}", report);
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        let queries = self
+            .in_flight
+            .drain(..)
+            .flat_map(|(_, records)| records)
+            .chain(self.records.drain(..))
+            .flat_map(|record| record.timestamps.to_vec())
+            .chain(self.free_queries.drain(..))
+            .collect::<Vec<_>>();
+        unsafe { gl::DeleteQueries(queries.len() as _, queries.as_ptr()) };
+
+        if let Some(csv) = &mut self.csv {
+            let _ = csv.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn repeated_scopes_are_summed() {
+        let totals = frame_totals(&[
+            ("ocean", 0, 1.0, 2.0),
+            ("fft", 1, 0.5, 1.0),
+            ("skybox", 0, 0.25, 0.5),
+            ("fft", 1, 0.5, 1.5),
+        ]);
+        assert_eq!(
+            totals,
+            vec![
+                ("ocean", 0, 1.0, 2.0),
+                ("fft", 1, 1.0, 2.5),
+                ("skybox", 0, 0.25, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn averages_over_window() {
+        let mut stat = Stat::new("hkt", 0);
+        stat.push(WINDOW as f32 + 2.0, WINDOW as f32 + 3.0);
+        for _ in 0..WINDOW {
+            stat.push(1.0, 2.0);
+        }
+        let average = stat.average();
+        assert_eq!((average.cpu_ms, average.gpu_ms), (1.0, 2.0));
+
+        let overall = stat.overall_average();
+        assert_eq!((overall.cpu_ms, overall.gpu_ms), (2.0, 3.0));
+    }
+}
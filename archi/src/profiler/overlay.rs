@@ -0,0 +1,132 @@
+//! Bars of the average time of each scope, in the corner of the screen. The
+//! rows are in the same order as the scopes reported to stderr.
+
+use super::Average;
+use crate::context::{BackBuffer, Context};
+use luminance::blending::{Blending, Equation, Factor};
+use luminance_derive::UniformInterface;
+use luminance_front::{
+    context::GraphicsContext,
+    pipeline::{PipelineGate, PipelineState, TextureBinding},
+    pixel::{Floating, RG32F},
+    render_state::RenderState,
+    shader::{Program, Uniform},
+    tess::Tess,
+    texture::{Dim2, GenMipmaps, MagFilter, MinFilter, Sampler, Texture},
+};
+
+/// Scopes beyond this many are left out.
+const MAX_ROWS: u32 = 32;
+
+const ROW_HEIGHT: f32 = 12.0;
+const WIDTH: f32 = 400.0;
+
+/// Milliseconds across the overlay, a frame at 60Hz.
+const SPAN: f32 = 1000.0 / 60.0;
+
+#[derive(UniformInterface)]
+struct OverlayInterface {
+    scope_times: Uniform<TextureBinding<Dim2, Floating>>,
+    rows: Uniform<i32>,
+    span: Uniform<f32>,
+    rect: Uniform<[f32; 4]>,
+}
+
+pub struct Overlay {
+    times: Texture<Dim2, RG32F>,
+    shader: Program<(), (), OverlayInterface>,
+    quad: Tess<()>,
+}
+
+impl Overlay {
+    pub fn new(context: &mut Context) -> anyhow::Result<Self> {
+        let sampler = Sampler {
+            mag_filter: MagFilter::Nearest,
+            min_filter: MinFilter::Nearest,
+            ..Default::default()
+        };
+        let times = context.new_texture([MAX_ROWS, 1], 0, sampler)?;
+
+        let shader = crate::shader::from_sources(
+            context,
+            None,
+            crate::shader_source!("./shaders/profiler_overlay.vert"),
+            None,
+            crate::shader_source!("./shaders/profiler_overlay.frag"),
+        )?;
+
+        Ok(Self {
+            times,
+            shader,
+            quad: crate::postprocess::quad(context)?,
+        })
+    }
+
+    /// Draw over what is in `back_buffer`, of size `viewport`.
+    pub fn render(
+        &mut self,
+        pipeline_gate: &mut PipelineGate,
+        back_buffer: &BackBuffer,
+        viewport: [u32; 2],
+        averages: &[Average],
+    ) -> anyhow::Result<()> {
+        let Self {
+            times,
+            shader,
+            quad,
+        } = self;
+
+        let rows = averages.len().min(MAX_ROWS as usize);
+        if rows == 0 {
+            return Ok(());
+        }
+
+        let mut pixels = vec![(0.0, 0.0); MAX_ROWS as usize];
+        for (pixel, average) in pixels.iter_mut().zip(averages) {
+            *pixel = (average.cpu_ms, average.gpu_ms);
+        }
+        times.upload(GenMipmaps::No, &pixels)?;
+
+        // Top left corner, a few pixels in
+        let [width, height] = viewport;
+        let to_ndc = |pixels: f32, size: u32| pixels / size as f32 * 2.0;
+        let rect = [
+            -1.0 + to_ndc(8.0, width),
+            1.0 - to_ndc(8.0 + rows as f32 * ROW_HEIGHT, height),
+            -1.0 + to_ndc(8.0 + WIDTH, width),
+            1.0 - to_ndc(8.0, height),
+        ];
+
+        pipeline_gate
+            .pipeline(
+                back_buffer,
+                &PipelineState::new().enable_clear_color(false),
+                |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                    let times = pipeline.bind_texture(times)?;
+                    shader_gate.shade(
+                        shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface.set(&uni.scope_times, times.binding());
+                            iface.set(&uni.rows, rows as i32);
+                            iface.set(&uni.span, SPAN);
+                            iface.set(&uni.rect, rect);
+
+                            let state = RenderState::default()
+                                .set_depth_test(None)
+                                .set_blending(Blending {
+                                    equation: Equation::Additive,
+                                    src: Factor::SrcAlpha,
+                                    dst: Factor::SrcAlphaComplement,
+                                });
+                            render_gate.render(&state, |mut tess_gate| {
+                                tess_gate.render(&*quad)
+                            })
+                        },
+                    )
+                },
+            )
+            .into_result()?;
+
+        Ok(())
+    }
+}
@@ -8,8 +8,11 @@ use luminance_front::shader::{
 use luminance_front::vertex::Semantics;
 use luminance_front::Backend;
 
+mod compute;
 mod preprocessor;
 
+pub use compute::{bound_texture_name, bound_texture_name_of, ComputeProgram};
+
 pub struct Preprocessor {
     inner: preprocessor::Preprocessor,
 }
@@ -32,10 +35,18 @@ impl Preprocessor {
             };
         }
 
+        add_source!("./shaders/include/constants.glsl");
         add_source!("./shaders/include/complex.glsl");
         add_source!("./shaders/include/tonemap.glsl");
         add_source!("./shaders/include/atmosphere.glsl");
         add_source!("./shaders/include/equirectangular.glsl");
+        add_source!("./shaders/include/cubemap.glsl");
+        add_source!("./shaders/include/brdf.glsl");
+        add_source!("./shaders/include/sh.glsl");
+        add_source!("./shaders/include/blue_noise.glsl");
+        add_source!("./shaders/include/fog.glsl");
+        add_source!("./shaders/include/ocean_displacement.glsl");
+        add_source!("./shaders/include/inspector.glsl");
 
         Self { inner }
     }
@@ -127,7 +138,10 @@ where
 
     let geom_stage: Option<&Stage> = geom_stage.as_ref();
 
-    let BuiltProgram { program, warnings } = context
+    let BuiltProgram {
+        mut program,
+        warnings,
+    } = context
         .new_shader_program()
         .from_stages(&vert_stage, tess_stage, geom_stage, &frag_stage)
         .with_context(|| {
@@ -154,9 +168,12 @@ where
         })?;
 
     for warning in warnings {
-        eprintln!("{}", warning);
+        tracing::warn!("{}", warning);
     }
 
+    let label = frag.name.trim_start_matches("./shaders/");
+    crate::debug::capture::label_program(context, &mut program, label)?;
+
     Ok(program)
 }
 
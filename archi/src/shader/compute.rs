@@ -0,0 +1,130 @@
+use super::ShaderSource;
+use crate::context::Context;
+use gl::types::{GLchar, GLenum, GLint, GLuint};
+
+/// A compute shader program. Luminance has no support for them, so it is
+/// built and used through raw GL calls, taking care to leave the state that
+/// luminance keeps track of as it was.
+pub struct ComputeProgram {
+    handle: GLuint,
+}
+
+fn info_log(
+    handle: GLuint,
+    get_iv: unsafe fn(GLuint, gl::types::GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, i32, *mut i32, *mut GLchar),
+) -> String {
+    unsafe {
+        let mut length = 0;
+        get_iv(handle, gl::INFO_LOG_LENGTH, &mut length);
+        let mut log = vec![0u8; length.max(1) as usize];
+        get_log(handle, length, std::ptr::null_mut(), log.as_mut_ptr() as _);
+        String::from_utf8_lossy(&log)
+            .trim_end_matches('\0')
+            .to_owned()
+    }
+}
+
+impl ComputeProgram {
+    /// Compile `source` after a `#define` for each of `defines`. Needs GL 4.3
+    /// or `GL_ARB_compute_shader`.
+    pub fn new(
+        context: &mut Context,
+        source: &ShaderSource,
+        defines: &[(&str, String)],
+    ) -> anyhow::Result<Self> {
+        let body = context.shader_preprocessor.inner.expand(source)?;
+
+        let mut text = String::from("#version 430 core\n");
+        for (name, value) in defines {
+            text.push_str(&format!("#define {} {}\n", name, value));
+        }
+        text.push_str(&body);
+        let text = std::ffi::CString::new(text)?;
+
+        unsafe {
+            let shader = gl::CreateShader(gl::COMPUTE_SHADER);
+            gl::ShaderSource(shader, 1, &text.as_ptr(), std::ptr::null());
+            gl::CompileShader(shader);
+
+            let mut status = 0;
+            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+            if status == gl::FALSE as GLint {
+                let log =
+                    info_log(shader, gl::GetShaderiv, gl::GetShaderInfoLog);
+                gl::DeleteShader(shader);
+                anyhow::bail!(
+                    "failed to compile compute shader {}:\n{}",
+                    source.name,
+                    log
+                );
+            }
+
+            let handle = gl::CreateProgram();
+            gl::AttachShader(handle, shader);
+            gl::LinkProgram(handle);
+            gl::DeleteShader(shader);
+
+            gl::GetProgramiv(handle, gl::LINK_STATUS, &mut status);
+            if status == gl::FALSE as GLint {
+                let log =
+                    info_log(handle, gl::GetProgramiv, gl::GetProgramInfoLog);
+                gl::DeleteProgram(handle);
+                anyhow::bail!(
+                    "failed to link compute shader {}:\n{}",
+                    source.name,
+                    log
+                );
+            }
+
+            crate::debug::capture::label_program_name(handle, source.name);
+
+            Ok(Self { handle })
+        }
+    }
+
+    /// Location of a uniform, -1 if the program does not use it, which GL
+    /// quietly ignores when setting it.
+    pub fn uniform_location(&self, name: &str) -> GLint {
+        let name = std::ffi::CString::new(name).unwrap();
+        unsafe { gl::GetUniformLocation(self.handle, name.as_ptr()) }
+    }
+
+    /// Run `f` with the program in use, then put the previous one back.
+    pub fn with<R>(&self, f: impl FnOnce() -> R) -> R {
+        let mut previous = 0;
+        unsafe {
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous);
+            gl::UseProgram(self.handle);
+        }
+        let result = f();
+        unsafe { gl::UseProgram(previous as GLuint) };
+        result
+    }
+}
+
+impl Drop for ComputeProgram {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteProgram(self.handle) };
+    }
+}
+
+/// Name of the 2D texture bound to a texture unit. Luminance does not hand
+/// out the names of its textures, but binding one in a pipeline says which
+/// unit to look in.
+pub fn bound_texture_name(unit: u32) -> GLuint {
+    bound_texture_name_of(unit, gl::TEXTURE_BINDING_2D)
+}
+
+/// Name of the texture bound to a texture unit, for the kind of texture that
+/// `binding` is the binding of, such as `GL_TEXTURE_BINDING_CUBE_MAP`.
+pub fn bound_texture_name_of(unit: u32, binding: GLenum) -> GLuint {
+    let (mut active, mut name) = (0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::ACTIVE_TEXTURE, &mut active);
+        gl::ActiveTexture(gl::TEXTURE0 + unit);
+        gl::GetIntegerv(binding, &mut name);
+        gl::ActiveTexture(active as u32);
+    }
+    name as GLuint
+}
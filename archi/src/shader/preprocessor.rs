@@ -52,18 +52,23 @@ impl Preprocessor {
     }
 
     pub fn expand(&mut self, source: &Source) -> Result<CowStr, Error> {
-        self.expand_recursive(source, &mut Default::default())
+        if let Some(expanded) = self.expanded.get(source.name) {
+            return Ok(expanded.clone());
+        }
+
+        let value = self.expand_recursive(source, &mut Default::default())?;
+        self.expanded.insert(source.name, value.clone());
+
+        Ok(value)
     }
 
+    // Headers are not cached, since what a header expands to depends on
+    // which other headers the including source has already pulled in.
     fn expand_recursive(
         &mut self,
         source: &Source,
         included_names: &mut BTreeSet<&str>,
     ) -> Result<CowStr, Error> {
-        if let Some(expanded) = self.expanded.get(source.name) {
-            return Ok(expanded.clone());
-        }
-
         let pragma = "#pragma include ";
         let has_pragma = source.body.lines().any(|l| l.starts_with(pragma));
 
@@ -103,8 +108,6 @@ impl Preprocessor {
             source.body.into()
         };
 
-        self.expanded.insert(source.name, value.clone());
-
         Ok(value)
     }
 }
@@ -289,6 +292,45 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn shared_header_across_sources() -> anyhow::Result<()> {
+        let mut p = Preprocessor::new();
+        p.add_header(Source {
+            name: "constants.glsl",
+            body: "#define PI=3",
+        });
+        p.add_header(Source {
+            name: "alpha.glsl",
+            body: "#pragma include \"constants.glsl\"\n#define ALPHA=1",
+        });
+        p.add_header(Source {
+            name: "beta.glsl",
+            body: "#pragma include \"constants.glsl\"\n#define BETA=1",
+        });
+
+        let both = &p.expand(&Source {
+            name: "both.glsl",
+            body: "#pragma include \"alpha.glsl\"\n\
+                #pragma include \"beta.glsl\"",
+        })?;
+
+        let expected = "#define PI=3\n\
+                        #define ALPHA=1\n\
+                        #define BETA=1\n";
+        assert!(sloc(both).eq(sloc(expected)));
+
+        let beta = &p.expand(&Source {
+            name: "beta_only.glsl",
+            body: "#pragma include \"beta.glsl\"",
+        })?;
+
+        let expected = "#define PI=3\n\
+                        #define BETA=1\n";
+        assert!(sloc(beta).eq(sloc(expected)));
+
+        Ok(())
+    }
+
     #[test]
     fn not_found() {
         let mut p = Preprocessor::new();
@@ -0,0 +1,18 @@
+in vec2 uv;
+
+out vec4 frag;
+
+uniform sampler2D input_texture;
+uniform sampler2D bloom_texture;
+uniform int levels;
+uniform float intensity;
+
+void main() {
+  vec3 scene = texture(input_texture, uv).rgb;
+
+  // Every level adds a full copy of the scene, so dividing by their count
+  // keeps the bloom at the same energy as the scene it replaces
+  vec3 bloom = texture(bloom_texture, uv).rgb / float(levels);
+
+  frag = vec4(mix(scene, bloom, intensity), 1.0);
+}
@@ -0,0 +1,58 @@
+#pragma include "tonemap.glsl"
+
+// 13 tap downsample from Jimenez' "Next Generation Post Processing in Call of
+// Duty: Advanced Warfare", optionally with its Karis average, which keeps
+// bright pixels from flickering as they move between texels of the smaller
+// levels
+
+in vec2 uv;
+
+out vec4 frag;
+
+uniform sampler2D input_texture;
+uniform int karis_average;
+
+vec3 sample_at(vec2 offset) {
+  vec2 texel = 1.0 / vec2(textureSize(input_texture, 0));
+  return texture(input_texture, uv + offset * texel).rgb;
+}
+
+// Weights a group of samples by inverse luminance, so that a single very
+// bright one such as a sun glint can not dominate the group. This does not
+// preserve energy: the group comes out darker than its plain average
+vec3 karis(vec3 a, vec3 b, vec3 c, vec3 d) {
+  float wa = 1.0 / (1.0 + dot(a, LUMA));
+  float wb = 1.0 / (1.0 + dot(b, LUMA));
+  float wc = 1.0 / (1.0 + dot(c, LUMA));
+  float wd = 1.0 / (1.0 + dot(d, LUMA));
+  return (a * wa + b * wb + c * wc + d * wd) / (wa + wb + wc + wd);
+}
+
+vec3 average(vec3 a, vec3 b, vec3 c, vec3 d) {
+  return karis_average != 0 ? karis(a, b, c, d) : (a + b + c + d) * 0.25;
+}
+
+void main() {
+  vec3 a = sample_at(vec2(-2.0, 2.0));
+  vec3 b = sample_at(vec2(0.0, 2.0));
+  vec3 c = sample_at(vec2(2.0, 2.0));
+  vec3 d = sample_at(vec2(-2.0, 0.0));
+  vec3 e = sample_at(vec2(0.0, 0.0));
+  vec3 f = sample_at(vec2(2.0, 0.0));
+  vec3 g = sample_at(vec2(-2.0, -2.0));
+  vec3 h = sample_at(vec2(0.0, -2.0));
+  vec3 i = sample_at(vec2(2.0, -2.0));
+  vec3 j = sample_at(vec2(-1.0, 1.0));
+  vec3 k = sample_at(vec2(1.0, 1.0));
+  vec3 l = sample_at(vec2(-1.0, -1.0));
+  vec3 m = sample_at(vec2(1.0, -1.0));
+
+  // Five overlapping boxes, the center one weighted the most
+  vec3 color = average(j, k, l, m) * 0.5
+    + average(a, b, d, e) * 0.125
+    + average(b, c, e, f) * 0.125
+    + average(d, e, g, h) * 0.125
+    + average(e, f, h, i) * 0.125;
+
+  frag = vec4(color, 1.0);
+}
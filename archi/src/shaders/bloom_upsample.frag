@@ -0,0 +1,24 @@
+in vec2 uv;
+
+out vec4 frag;
+
+uniform sampler2D coarse_texture; // accumulated bloom of the level below
+uniform sampler2D fine_texture; // downsampled scene at this level
+uniform float radius; // in texels of the coarse level
+
+// 3x3 tent filter over the coarse level, added to the fine one
+void main() {
+  vec2 texel = radius / vec2(textureSize(coarse_texture, 0));
+
+  vec3 color = texture(coarse_texture, uv).rgb * 4.0;
+  color += texture(coarse_texture, uv + vec2(-texel.x, 0.0)).rgb * 2.0;
+  color += texture(coarse_texture, uv + vec2(texel.x, 0.0)).rgb * 2.0;
+  color += texture(coarse_texture, uv + vec2(0.0, -texel.y)).rgb * 2.0;
+  color += texture(coarse_texture, uv + vec2(0.0, texel.y)).rgb * 2.0;
+  color += texture(coarse_texture, uv + vec2(-texel.x, -texel.y)).rgb;
+  color += texture(coarse_texture, uv + vec2(texel.x, -texel.y)).rgb;
+  color += texture(coarse_texture, uv + vec2(-texel.x, texel.y)).rgb;
+  color += texture(coarse_texture, uv + vec2(texel.x, texel.y)).rgb;
+
+  frag = vec4(texture(fine_texture, uv).rgb + color / 16.0, 1.0);
+}
@@ -5,19 +5,24 @@ in vec2 uv;
 uniform sampler2D twiddle_indices;
 uniform sampler2D input_texture;
 
-out vec2 frag;
+// Two complex signals, in rg and ba, transformed side by side
+out vec4 frag;
 
 uniform int stage;
 uniform int direction;
+// +1 for the inverse transform, -1 for the forward one
+uniform float exponent_sign;
+// Applied to the sum, to keep half precision results in range
+uniform float scale;
 
 vec4 get_pixel(sampler2D sampler, ivec2 uv) {
   vec2 size = textureSize(sampler, 0);
   return texture(sampler, (uv + 0.5) / size);
 }
 
-vec2 get_input_pixel(ivec2 uv) {
+vec4 get_input_pixel(ivec2 uv) {
   if (direction != 0) uv = uv.yx; // Flip coordinates
-  return get_pixel(input_texture, uv).rg;
+  return get_pixel(input_texture, uv);
 }
 
 void main() {
@@ -25,12 +30,12 @@ void main() {
   if (direction != 0) pixel_coord = pixel_coord.yx; // Flip coordinates
 
   vec4 twiddle = get_pixel(twiddle_indices, ivec2(stage, pixel_coord.x)).rgba;
-  vec2 omega = twiddle.xy;
-  vec2 p = get_input_pixel(ivec2(twiddle.z, pixel_coord.y));
-  vec2 q = get_input_pixel(ivec2(twiddle.w, pixel_coord.y));
+  vec2 omega = vec2(twiddle.x, exponent_sign * twiddle.y);
+  vec4 p = get_input_pixel(ivec2(twiddle.z, pixel_coord.y));
+  vec4 q = get_input_pixel(ivec2(twiddle.w, pixel_coord.y));
 
   // Butterfly operation
-  vec2 H = p + cmul(omega, q);
+  vec4 H = p + vec4(cmul(omega, q.xy), cmul(omega, q.zw));
 
-  frag = H;
+  frag = H * scale;
 }
@@ -0,0 +1,15 @@
+#pragma include "equirectangular.glsl"
+#pragma include "cubemap.glsl"
+
+in vec2 layer_uv;
+flat in int layer;
+
+out vec4 frag;
+
+uniform sampler2D equirectangular_texture;
+
+void main() {
+  vec3 direction = cube_face_direction(layer, layer_uv * 2.0 - 1.0);
+  vec2 uv = equirectangular(direction);
+  frag = vec4(textureLod(equirectangular_texture, uv, 0.0).rgb, 1.0);
+}
@@ -0,0 +1,33 @@
+#pragma include "blue_noise.glsl"
+
+in vec2 uv;
+
+out vec4 frag;
+
+uniform sampler2D input_texture;
+uniform float levels; // quantisation levels of the output, 255 for 8 bits
+
+vec3 linear_to_srgb(vec3 c) {
+  return mix(12.92 * c, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055,
+    step(0.0031308, c));
+}
+
+vec3 srgb_to_linear(vec3 c) {
+  return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)),
+    step(0.04045, c));
+}
+
+void main() {
+  vec3 color = clamp(texture(input_texture, uv).rgb, 0.0, 1.0);
+
+  // Summing both channels gives triangular noise, which unlike uniform
+  // noise leaves no trace of the signal in the noise level
+  vec4 noise = blue_noise(ivec2(gl_FragCoord.xy));
+  float offset = noise.x + noise.y - 1.0;
+
+  // The output is sRGB encoded on write, so the noise is added in sRGB
+  // space where it is measured in quantisation steps
+  vec3 srgb = linear_to_srgb(color) + offset / levels;
+
+  frag = vec4(srgb_to_linear(clamp(srgb, 0.0, 1.0)), 1.0);
+}
@@ -0,0 +1,46 @@
+uniform sampler2D histogram;
+uniform sampler2D previous_exposure;
+
+uniform float min_log_luminance;
+uniform float max_log_luminance;
+uniform float low_percentile;
+uniform float high_percentile;
+uniform float key;
+uniform float adaptation; // fraction of the way to go towards the target
+
+out vec4 frag;
+
+void main() {
+  int bins = textureSize(histogram, 0).x;
+
+  float total = 0.0;
+  for (int i = 0; i < bins; i++) {
+    total += texelFetch(histogram, ivec2(i, 0), 0).r;
+  }
+
+  // Average log luminance, leaving out the darkest and brightest samples
+  float low = total * low_percentile;
+  float high = total * high_percentile;
+  float seen = 0.0;
+  float sum = 0.0;
+  float count = 0.0;
+  for (int i = 0; i < bins; i++) {
+    float n = texelFetch(histogram, ivec2(i, 0), 0).r;
+    float inside = max(0.0, min(seen + n, high) - max(seen, low));
+    float t = (float(i) + 0.5) / float(bins);
+    sum += inside * mix(min_log_luminance, max_log_luminance, t);
+    count += inside;
+    seen += n;
+  }
+
+  float average = count > 0.0 ? sum / count : 0.0;
+  float target = log2(key) - average;
+
+  // Adapt in stops, so that brightening and darkening feel the same
+  float previous = texelFetch(previous_exposure, ivec2(0), 0).r;
+  float exposure = previous > 0.0
+    ? mix(log2(previous), target, adaptation)
+    : target;
+
+  frag = vec4(exp2(exposure));
+}
@@ -0,0 +1,55 @@
+#pragma include "tonemap.glsl"
+
+// FXAA as in Timothy Lottes' original PC version, on tonemapped input
+
+in vec2 uv;
+
+out vec4 frag;
+
+uniform sampler2D input_texture;
+uniform float span_max;
+uniform float reduce_min;
+uniform float reduce_mul;
+
+// Edges are found in perceptual rather than linear luminance
+float luma(vec3 color) {
+  return sqrt(dot(color, LUMA));
+}
+
+void main() {
+  vec2 texel = 1.0 / vec2(textureSize(input_texture, 0));
+
+  vec3 rgb_m = texture(input_texture, uv).rgb;
+  float luma_m = luma(rgb_m);
+  float luma_nw = luma(texture(input_texture, uv + vec2(-1.0, -1.0) * texel).rgb);
+  float luma_ne = luma(texture(input_texture, uv + vec2(1.0, -1.0) * texel).rgb);
+  float luma_sw = luma(texture(input_texture, uv + vec2(-1.0, 1.0) * texel).rgb);
+  float luma_se = luma(texture(input_texture, uv + vec2(1.0, 1.0) * texel).rgb);
+
+  float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+  float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+  // Blur along the edge, perpendicular to the luminance gradient
+  vec2 dir = vec2(
+    (luma_sw + luma_se) - (luma_nw + luma_ne),
+    (luma_nw + luma_sw) - (luma_ne + luma_se));
+
+  float dir_reduce = max(
+    (luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * reduce_mul,
+    reduce_min);
+  float rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+  dir = clamp(dir * rcp_dir_min, -span_max, span_max) * texel;
+
+  vec3 rgb_a = 0.5 * (
+    texture(input_texture, uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+    texture(input_texture, uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+  vec3 rgb_b = rgb_a * 0.5 + 0.25 * (
+    texture(input_texture, uv - dir * 0.5).rgb +
+    texture(input_texture, uv + dir * 0.5).rgb);
+
+  // The wider blur overshot the local range, so it crossed another edge
+  float luma_b = luma(rgb_b);
+  vec3 rgb = (luma_b < luma_min || luma_b > luma_max) ? rgb_a : rgb_b;
+
+  frag = vec4(rgb, 1.0);
+}
@@ -0,0 +1,5 @@
+out vec4 frag;
+
+void main() {
+  frag = vec4(1.0);
+}
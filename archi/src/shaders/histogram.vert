@@ -0,0 +1,24 @@
+#pragma include "tonemap.glsl"
+
+uniform sampler2D hdr_texture;
+uniform int grid_size;
+uniform int bins;
+uniform float min_log_luminance;
+uniform float max_log_luminance;
+
+// Every vertex samples one point of the scene on a regular grid and is
+// placed on the histogram bin of its luminance, where additive blending
+// counts it
+void main() {
+  ivec2 cell = ivec2(gl_VertexID % grid_size, gl_VertexID / grid_size);
+  vec2 uv = (vec2(cell) + 0.5) / float(grid_size);
+
+  vec3 color = textureLod(hdr_texture, uv, 0.0).rgb;
+  float luminance = max(dot(color, LUMA), 0.000001);
+
+  float t = (log2(luminance) - min_log_luminance)
+    / (max_log_luminance - min_log_luminance);
+  float bin = min(floor(clamp(t, 0.0, 1.0) * float(bins)), float(bins - 1));
+
+  gl_Position = vec4((bin + 0.5) / float(bins) * 2.0 - 1.0, 0.0, 0.0, 1.0);
+}
@@ -6,9 +6,12 @@ in vec2 uv;
 
 uniform sampler2D h0k_texture;
 
-out vec2 hkt_dx;
-out vec2 hkt_dy;
-out vec2 hkt_dz;
+// The spectra of the x and z displacement packed into one complex signal,
+// then that of the y displacement. All three displacements are real, so
+// they come out of the inverse FFT as the real and imaginary parts of rg,
+// and the real part of ba. The imaginary part of ba is left for another
+// real map.
+out vec4 hkt;
 
 uniform int n = 512;
 uniform int scale = 1000;
@@ -36,13 +39,17 @@ void main(void) {
   vec2 exp_iwt_inv = vec2(cosinus, -sinus);
 
   // dy
-  hkt_dy = cmul(fou_amp, exp_iwt) + cmul(fou_amp_conj, exp_iwt_inv);
+  vec2 hkt_dy = cmul(fou_amp, exp_iwt) + cmul(fou_amp_conj, exp_iwt_inv);
 
   // dx
   vec2 dx = vec2(0.0, -k.x / magnitude);
-  hkt_dx = cmul(dx, hkt_dy);
+  vec2 hkt_dx = cmul(dx, hkt_dy);
 
   // dz
   vec2 dz = vec2(0.0, -k.y / magnitude);
-  hkt_dz = cmul(dz, hkt_dy);
+  vec2 hkt_dz = cmul(dz, hkt_dy);
+
+  // dx + i dz
+  hkt.xy = hkt_dx + vec2(-hkt_dz.y, hkt_dz.x);
+  hkt.zw = hkt_dy;
 }
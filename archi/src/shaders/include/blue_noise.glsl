@@ -0,0 +1,17 @@
+// Blue noise shared between effects. Shaders including this need
+// blue_noise_texture bound to noise::BlueNoise::texture and
+// blue_noise_frame set to noise::BlueNoise::frame of the current frame.
+
+uniform sampler2D blue_noise_texture;
+uniform int blue_noise_frame;
+
+const float GOLDEN_RATIO_CONJUGATE = 0.6180339887;
+
+// Four independent values, uniform in [0, 1) and blue over the screen. Every
+// frame shifts them along the golden ratio sequence, which keeps each pixel
+// well distributed over time as well
+vec4 blue_noise(ivec2 pixel) {
+  ivec2 size = textureSize(blue_noise_texture, 0);
+  vec4 noise = texelFetch(blue_noise_texture, pixel % size, 0);
+  return fract(noise + GOLDEN_RATIO_CONJUGATE * float(blue_noise_frame));
+}
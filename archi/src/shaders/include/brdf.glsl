@@ -0,0 +1,35 @@
+#pragma include "constants.glsl"
+
+// GGX (Trowbridge-Reitz) normal distribution function
+float d_ggx(float n_dot_h, float alpha) {
+  float alpha_sq = alpha * alpha;
+  float d = n_dot_h * n_dot_h * (alpha_sq - 1.0) + 1.0;
+  return alpha_sq / (PI * d * d);
+}
+
+// Height correlated Smith visibility term, G / (4 * n_dot_l * n_dot_v)
+float v_smith_ggx(float n_dot_l, float n_dot_v, float alpha) {
+  float alpha_sq = alpha * alpha;
+  float g_l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - alpha_sq) + alpha_sq);
+  float g_v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - alpha_sq) + alpha_sq);
+  return 0.5 / max(g_l + g_v, 0.00001);
+}
+
+float fresnel_schlick(float f0, float cos_theta) {
+  float m = clamp(1.0 - cos_theta, 0.0, 1.0);
+  float m2 = m * m;
+  return f0 + (1.0 - f0) * m2 * m2 * m;
+}
+
+// Exact Fresnel reflectance of unpolarized light hitting a dielectric
+// interface, where eta is the ratio of the indices of refraction.
+float fresnel_dielectric(float cos_i, float eta) {
+  float c = abs(cos_i);
+  float g_sq = eta * eta - 1.0 + c * c;
+  if (g_sq < 0.0) return 1.0; // total internal reflection
+
+  float g = sqrt(g_sq);
+  float a = (g - c) / (g + c);
+  float b = (c * (g + c) - 1.0) / (c * (g - c) + 1.0);
+  return 0.5 * a * a * (1.0 + b * b);
+}
@@ -0,0 +1,14 @@
+// Direction through the point st in [-1, 1] on a cubemap face, with faces
+// numbered +x, -x, +y, -y, +z, -z as in skybox/cube.rs
+vec3 cube_face_direction(int face, vec2 st) {
+  float s = st.x;
+  float t = st.y;
+  switch (face) {
+    case 0: return vec3(1.0, -t, -s);
+    case 1: return vec3(-1.0, -t, s);
+    case 2: return vec3(s, 1.0, t);
+    case 3: return vec3(s, -1.0, -t);
+    case 4: return vec3(s, -t, 1.0);
+    default: return vec3(-s, -t, -1.0);
+  }
+}
@@ -7,3 +7,13 @@ vec2 equirectangular(vec3 direction) {
     uv += 0.5;
     return uv;
 }
+
+vec3 equirectangular_direction(vec2 uv) {
+    vec2 angles = (uv - 0.5) / INV_ATAN;
+    float azimuth = angles.x;
+    float elevation = -angles.y;
+    return vec3(
+        cos(elevation) * cos(azimuth),
+        sin(elevation),
+        cos(elevation) * sin(azimuth));
+}
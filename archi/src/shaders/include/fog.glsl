@@ -0,0 +1,33 @@
+#pragma include "constants.glsl"
+
+// Exponential height fog. Density falls off with height above the base
+// height, and extinction is per colour channel, so that distant surfaces
+// turn red the way they do through real air, blue being absorbed fastest.
+
+uniform vec3 fog_extinction; // per meter, at the base height
+uniform float fog_height_falloff; // per meter
+uniform float fog_base_height;
+uniform float fog_sun_scattering;
+uniform float fog_anisotropy; // Henyey-Greenstein g of the sun glow
+
+// Fraction of light that makes it from position to the eye, integrating the
+// density analytically along the ray
+vec3 fog_transmittance(vec3 eye, vec3 position) {
+  vec3 ray = position - eye;
+  float distance = length(ray);
+  float rise = ray.y * fog_height_falloff;
+
+  // Integral of exp(-falloff * height) over the ray, relative to its value
+  // at the eye, which tends to 1 as the ray turns horizontal
+  float gradient = abs(rise) > 0.0001 ? (1.0 - exp(-rise)) / rise : 1.0;
+  float density =
+    exp(-fog_height_falloff * (eye.y - fog_base_height)) * gradient;
+
+  return exp(-fog_extinction * density * distance);
+}
+
+float fog_phase(float cos_theta) {
+  float g = fog_anisotropy;
+  float denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+  return (1.0 - g * g) / (4.0 * PI * denominator * sqrt(denominator));
+}
@@ -0,0 +1,53 @@
+// The texture inspector, for inspector_*.frag, which each define texel_value
+// for their kind of texture.
+
+// Window pixels from the middle of the viewport to its corner
+uniform vec2 half_viewport;
+// Texel coordinates at the middle of the viewport
+uniform vec2 center;
+// Window pixels per texel
+uniform float zoom;
+uniform ivec2 size;
+// Array layer, or cubemap face numbered as in cubemap.glsl
+uniform int layer;
+// -1 for red, green and blue, or the index of a single channel
+uniform int channel;
+// Values mapped to black and white
+uniform vec2 range;
+// Output the texel as it is, for reading back
+uniform bool raw;
+uniform ivec2 hovered;
+
+out vec4 frag;
+
+vec4 texel_value(ivec2 texel);
+
+void main() {
+  vec2 position = center + (gl_FragCoord.xy - half_viewport) / zoom;
+  ivec2 texel = ivec2(floor(position));
+
+  if (any(lessThan(texel, ivec2(0))) || any(greaterThanEqual(texel, size))) {
+    vec2 cell = floor(gl_FragCoord.xy / 16.0);
+    frag = vec4(vec3(0.1 + 0.05 * mod(cell.x + cell.y, 2.0)), 1.0);
+    return;
+  }
+
+  vec4 value = texel_value(texel);
+  if (raw) {
+    frag = value;
+    return;
+  }
+
+  vec4 remapped = (value - range.x) / (range.y - range.x);
+  vec3 color = channel < 0 ? remapped.rgb : vec3(remapped[channel]);
+
+  // Outline the texel under the cursor, once texels are big enough
+  vec2 within = fract(position) * zoom;
+  bool edge = any(lessThan(within, vec2(1.0))) ||
+              any(greaterThan(within, vec2(zoom - 1.0)));
+  if (texel == hovered && zoom >= 8.0 && edge) {
+    color = vec3(1.0, 0.0, 1.0);
+  }
+
+  frag = vec4(clamp(color, 0.0, 1.0), 1.0);
+}
@@ -0,0 +1,17 @@
+// Displacement of the ocean surface from the FFT offset maps, shared by the
+// tessellated and the vertex displaced grids.
+
+// x, z and y displacement in r, g and b, see hkt.frag
+uniform sampler2D displacement_map;
+
+// Meters of ocean covered by one tile of the offset maps
+const float PATCH_SIZE = 16.0;
+
+vec2 ocean_uv(vec2 world_xz) {
+  return world_xz / PATCH_SIZE;
+}
+
+vec3 ocean_displacement(vec2 uv) {
+  vec4 map = texture(displacement_map, uv);
+  return vec3(-map.x, map.z, -map.y);
+}
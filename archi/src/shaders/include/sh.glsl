@@ -0,0 +1,34 @@
+#pragma include "constants.glsl"
+
+// Real spherical harmonics basis up to band 2, in the same order as
+// skybox/sh.rs
+void sh_basis(vec3 d, out float y[9]) {
+  y[0] = 0.282095;
+  y[1] = 0.488603 * d.y;
+  y[2] = 0.488603 * d.z;
+  y[3] = 0.488603 * d.x;
+  y[4] = 1.092548 * d.x * d.y;
+  y[5] = 1.092548 * d.y * d.z;
+  y[6] = 0.315392 * (3.0 * d.z * d.z - 1.0);
+  y[7] = 1.092548 * d.x * d.z;
+  y[8] = 0.546274 * (d.x * d.x - d.y * d.y);
+}
+
+// Irradiance arriving at a surface facing `normal`, from SH9 radiance
+// coefficients stored in the first row of a 9 pixel wide texture
+vec3 sh_irradiance(sampler2D coefficients, vec3 normal) {
+  const float band_factors[9] = float[9](
+    3.141593,
+    2.094395, 2.094395, 2.094395,
+    0.785398, 0.785398, 0.785398, 0.785398, 0.785398);
+
+  float y[9];
+  sh_basis(normal, y);
+
+  vec3 irradiance = vec3(0.0);
+  for (int i = 0; i < 9; i++) {
+    vec3 c = texelFetch(coefficients, ivec2(i, 0), 0).rgb;
+    irradiance += c * band_factors[i] * y[i];
+  }
+  return max(irradiance, vec3(0.0));
+}
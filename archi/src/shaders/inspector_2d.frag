@@ -0,0 +1,7 @@
+#pragma include "inspector.glsl"
+
+uniform sampler2D texture_2d;
+
+vec4 texel_value(ivec2 texel) {
+  return texelFetch(texture_2d, texel, 0);
+}
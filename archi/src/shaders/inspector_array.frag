@@ -0,0 +1,7 @@
+#pragma include "inspector.glsl"
+
+uniform sampler2DArray texture_array;
+
+vec4 texel_value(ivec2 texel) {
+  return texelFetch(texture_array, ivec3(texel, layer), 0);
+}
@@ -0,0 +1,9 @@
+#pragma include "inspector.glsl"
+#pragma include "cubemap.glsl"
+
+uniform samplerCube texture_cube;
+
+vec4 texel_value(ivec2 texel) {
+  vec2 st = (vec2(texel) + 0.5) / vec2(size) * 2.0 - 1.0;
+  return textureLod(texture_cube, cube_face_direction(layer, st), 0.0);
+}
@@ -0,0 +1,50 @@
+// The value of the texel under the cursor, as text in a corner of the
+// inspector. Glyphs are 3x5 font pixels in cells of 4x6.
+
+out vec4 frag;
+
+// Indices into FONT, a texel per character with the first line in row 0
+uniform sampler2D text;
+// Bottom left corner of the readout, in window pixels
+uniform vec2 origin;
+// Window pixels per font pixel
+uniform float scale;
+
+// A bit per font pixel, row by row from the top left, in the order of
+// inspector::GLYPHS
+const int FONT[24] = int[](
+  0, 31599, 11415, 29671, 29647, 23497, 31183, 31215, 29257, 31727, 31695,
+  2, 448, 1488, 20, 1899, 19822, 1507, 14756, 1886, 8338, 25751, 3437, 1828
+);
+
+const ivec2 CELL = ivec2(4, 6);
+
+void main() {
+  ivec2 size = textureSize(text, 0);
+  ivec2 extent = size * CELL;
+
+  // A font pixel of margin around the text
+  ivec2 pixel = ivec2(floor((gl_FragCoord.xy - origin) / scale)) - 1;
+  if (any(lessThan(pixel, ivec2(-1))) || any(greaterThan(pixel, extent))) {
+    discard;
+  }
+
+  frag = vec4(0.0, 0.0, 0.0, 0.7);
+  if (any(lessThan(pixel, ivec2(0))) || any(greaterThanEqual(pixel, extent))) {
+    return;
+  }
+
+  ivec2 cell = pixel / CELL;
+  ivec2 within = pixel % CELL;
+  // The bottom row and the right column of each cell are spacing
+  int row = CELL.y - 1 - within.y;
+  if (within.x == 3 || row == 5) {
+    return;
+  }
+
+  int glyph = int(texelFetch(text, ivec2(cell.x, size.y - 1 - cell.y), 0).r);
+  int bit = 14 - (row * 3 + within.x);
+  if (((FONT[glyph] >> bit) & 1) != 0) {
+    frag = vec4(1.0);
+  }
+}
@@ -1,16 +1,24 @@
-in vec2 uv;
-
 uniform sampler2D input_texture;
-uniform uint n;
 
-out vec2 frag;
+// Normalisation of the transform
+uniform float scale;
+// Negate every other pixel in a checkerboard pattern, for the inverse of a
+// spectrum with the zero frequency in the middle
+uniform int checkerboard;
+// Move the corner to the middle, for a forward transform to a spectrum with
+// the zero frequency there
+uniform int shift;
+
+out vec4 frag;
 
 void main() {
-  vec2 xy = gl_FragCoord.xy - 0.5;
+  ivec2 xy = ivec2(gl_FragCoord.xy);
+  ivec2 size = textureSize(input_texture, 0);
+
+  ivec2 source = shift != 0 ? (xy + size / 2) % size : xy;
+  vec4 h = texelFetch(input_texture, source, 0) * scale;
 
-  // negate every other pixel in a checkerboard-like pattern
-  float perm = mod(dot(xy, xy), 2) * -2.0 + 1.0;
+  if (checkerboard != 0 && ((xy.x + xy.y) & 1) != 0) h = -h;
 
-  vec2 h = texture(input_texture, uv).xy;
-  frag = perm * h / float(n * n);
+  frag = h;
 }
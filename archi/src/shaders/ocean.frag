@@ -1,5 +1,6 @@
-#pragma include "tonemap.glsl"
-#pragma include "equirectangular.glsl"
+#pragma include "brdf.glsl"
+#pragma include "sh.glsl"
+#pragma include "fog.glsl"
 
 layout (location = 0) in vec2 uv;
 layout (location = 1) in vec3 position;
@@ -8,15 +9,54 @@ out vec4 frag;
 
 uniform vec3 camera_pos;
 
-uniform sampler2D ymap;
-uniform sampler2D sky_texture;
-uniform float exposure;
-
-vec3 sky(vec3 direction) {
-  vec2 uv = equirectangular(normalize(direction));
-  vec3 atmosphere_color = texture(sky_texture, uv).rgb;
+uniform sampler2D displacement_map;
+uniform samplerCube sky_texture;
+// Prefiltered levels of increasing roughness, see Skybox::specular
+uniform samplerCube specular_1;
+uniform samplerCube specular_2;
+uniform samplerCube specular_3;
+uniform samplerCube specular_4;
+uniform samplerCube specular_5;
+uniform int specular_levels;
+uniform sampler2D irradiance_sh;
+
+uniform vec3 sun_direction;
+uniform vec3 sun_color;
+uniform float sun_angular_radius;
+uniform float slope_variance; // slope variance of the unresolved waves
+
+uniform float ior;
+uniform vec3 water_absorption; // per meter
+uniform vec3 water_scattering; // per meter
+uniform vec3 subsurface_color;
+uniform float subsurface_height; // crest height at which the glow saturates
+
+const int TERM_COMBINED = 0;
+const int TERM_FRESNEL = 1;
+const int TERM_REFLECTION = 2;
+const int TERM_SUN = 3;
+const int TERM_WATER_BODY = 4;
+const int TERM_SUBSURFACE = 5;
+uniform int shading_term;
+
+vec3 sky(vec3 direction, int level) {
+  switch (level) {
+    case 0: return texture(sky_texture, direction).rgb;
+    case 1: return texture(specular_1, direction).rgb;
+    case 2: return texture(specular_2, direction).rgb;
+    case 3: return texture(specular_3, direction).rgb;
+    case 4: return texture(specular_4, direction).rgb;
+    default: return texture(specular_5, direction).rgb;
+  }
+}
 
-  return tonemap(atmosphere_color, exposure);
+// The sky as reflected by a surface of the given roughness, blended between
+// the two nearest prefiltered levels
+vec3 sky_specular(vec3 direction, float alpha) {
+  float level = clamp(sqrt(alpha), 0.0, 1.0) * float(specular_levels);
+  int lower = int(floor(level));
+  int upper = min(lower + 1, specular_levels);
+  return mix(sky(direction, lower), sky(direction, upper), fract(level));
 }
 
 vec3 sobel_normal() {
@@ -26,16 +66,16 @@ vec3 sobel_normal() {
   // |     |     |
   // z5 -- z6 -- z7
 
-  float texel = 1.0 / textureSize(ymap, 0).x;
+  float texel = 1.0 / textureSize(displacement_map, 0).x;
 
-  float z0 = texture(ymap, uv + vec2(-texel, -texel)).r;
-  float z1 = texture(ymap, uv + vec2(     0, -texel)).r;
-  float z2 = texture(ymap, uv + vec2( texel, -texel)).r;
-  float z3 = texture(ymap, uv + vec2(-texel,      0)).r;
-  float z4 = texture(ymap, uv + vec2( texel,      0)).r;
-  float z5 = texture(ymap, uv + vec2(-texel,  texel)).r;
-  float z6 = texture(ymap, uv + vec2(     0,  texel)).r;
-  float z7 = texture(ymap, uv + vec2( texel,  texel)).r;
+  float z0 = texture(displacement_map, uv + vec2(-texel, -texel)).b;
+  float z1 = texture(displacement_map, uv + vec2(     0, -texel)).b;
+  float z2 = texture(displacement_map, uv + vec2( texel, -texel)).b;
+  float z3 = texture(displacement_map, uv + vec2(-texel,      0)).b;
+  float z4 = texture(displacement_map, uv + vec2( texel,      0)).b;
+  float z5 = texture(displacement_map, uv + vec2(-texel,  texel)).b;
+  float z6 = texture(displacement_map, uv + vec2(     0,  texel)).b;
+  float z7 = texture(displacement_map, uv + vec2( texel,  texel)).b;
 
   vec3 normal;
 
@@ -46,6 +86,83 @@ vec3 sobel_normal() {
   return normalize(normal);
 }
 
+// GGX roughness of the surface as seen from this pixel. Waves that are too
+// small to be resolved by the displacement maps, or that vary faster than the
+// pixel footprint, show up as slope variance rather than as a normal, and
+// smear the reflection of the sun into a stable glitter instead of aliasing.
+float roughness(vec3 normal) {
+  vec2 slope = normal.xz / normal.y;
+  vec2 slope_dx = dFdx(slope);
+  vec2 slope_dy = dFdy(slope);
+  float variance = slope_variance
+    + 0.5 * (dot(slope_dx, slope_dx) + dot(slope_dy, slope_dy));
+
+  // GGX alpha for the D and G terms, set to the Beckmann roughness of the
+  // slope variance: at equal alpha the two distributions have nearly the same
+  // peak, and only differ in GGX's longer tail. Widened by the size of the
+  // sun disc so that a mirror-flat surface still reflects something.
+  float alpha_sq = 2.0 * variance + sun_angular_radius * sun_angular_radius;
+  return clamp(sqrt(alpha_sq), 0.001, 1.0);
+}
+
+vec3 sun_specular(vec3 normal, vec3 look_dir, float alpha) {
+  vec3 light_dir = normalize(sun_direction);
+  vec3 half_dir = normalize(light_dir + look_dir);
+
+  float n_dot_l = max(dot(normal, light_dir), 0.0);
+  float n_dot_v = max(dot(normal, look_dir), 0.0001);
+  float n_dot_h = max(dot(normal, half_dir), 0.0);
+  float v_dot_h = max(dot(look_dir, half_dir), 0.0);
+
+  float d = d_ggx(n_dot_h, alpha);
+  float v = v_smith_ggx(n_dot_l, n_dot_v, alpha);
+  float f = fresnel_dielectric(v_dot_h, ior);
+
+  return sun_color * d * v * f * n_dot_l;
+}
+
+// Light that has entered the water, scattered and come back out, assuming
+// deep water lit evenly from above.
+vec3 water_body() {
+  vec3 albedo = water_scattering / (water_absorption + water_scattering);
+  vec3 sun_dir = normalize(sun_direction);
+  vec3 up = vec3(0.0, 1.0, 0.0);
+  vec3 irradiance = sh_irradiance(irradiance_sh, up);
+  irradiance += sun_color * max(sun_dir.y, 0.0);
+  return albedo * irradiance / PI;
+}
+
+// Sunlight passing through the thin upper part of a wave, towards the viewer.
+// Only crests above the mean water level are thin enough for this to show.
+vec3 subsurface(vec3 normal, vec3 look_dir, float height) {
+  vec3 sun_dir = normalize(sun_direction);
+
+  float thinness = clamp(height / subsurface_height, 0.0, 1.0);
+  float towards_sun = pow(max(dot(-look_dir, sun_dir), 0.0), 4.0);
+  float backlit = pow(0.5 - 0.5 * dot(normal, sun_dir), 3.0);
+  float grazing = pow(max(dot(look_dir, normal), 0.0), 2.0);
+
+  float amount = thinness * towards_sun * backlit + 0.05 * thinness * grazing;
+  return amount * subsurface_color * sun_color;
+}
+
+// Light scattered towards the eye by the air between it and the surface.
+// Looking down at the water there is no sky behind it, so the colour of the
+// sky just above the horizon stands in for the light the air picks up; far
+// away this makes the ocean fade into the horizon instead of ending at it.
+vec3 aerial_perspective(vec3 color, vec3 look_dir) {
+  vec3 transmittance = fog_transmittance(camera_pos, position);
+
+  vec3 view_dir = -look_dir;
+  vec3 horizon_dir = normalize(vec3(view_dir.x, 0.02, view_dir.z));
+  vec3 horizon = sky_specular(horizon_dir, 0.5);
+
+  float cos_theta = dot(view_dir, normalize(sun_direction));
+  vec3 sun_glow = fog_sun_scattering * fog_phase(cos_theta) * sun_color;
+
+  return color * transmittance + (horizon + sun_glow) * (1.0 - transmittance);
+}
+
 void main() {
   vec3 world_normal = sobel_normal();
   vec3 look_dir = normalize(camera_pos - position);
@@ -53,14 +170,34 @@ void main() {
   vec3 reflected_dir = reflect(-look_dir, world_normal);
   reflected_dir.y = abs(reflected_dir.y);
 
-  vec3 reflection = sky(reflected_dir);
-
-  float fresnel = dot(look_dir, world_normal);
-  vec3 water_color = vec3(0.0, 0.1, 0.05);
-
-  frag.rgb = mix(reflection, water_color, fresnel);
-  frag.rgb *= 0.00000001;
-  frag.rgb += reflection;
+  float alpha = roughness(world_normal);
+  float fresnel = fresnel_dielectric(dot(look_dir, world_normal), ior);
+
+  vec3 reflection = fresnel * sky_specular(reflected_dir, alpha);
+  vec3 sun = sun_specular(world_normal, look_dir, alpha);
+  vec3 water = (1.0 - fresnel) * water_body();
+  vec3 crests = (1.0 - fresnel) * subsurface(world_normal, look_dir, position.y);
+
+  switch (shading_term) {
+    case TERM_FRESNEL:
+      frag.rgb = vec3(fresnel);
+      break;
+    case TERM_REFLECTION:
+      frag.rgb = reflection;
+      break;
+    case TERM_SUN:
+      frag.rgb = sun;
+      break;
+    case TERM_WATER_BODY:
+      frag.rgb = water;
+      break;
+    case TERM_SUBSURFACE:
+      frag.rgb = crests;
+      break;
+    default:
+      frag.rgb = aerial_perspective(reflection + sun + water + crests, look_dir);
+      break;
+  }
 
   frag.a = 1.0;
 }
@@ -0,0 +1,32 @@
+#pragma include "ocean_displacement.glsl"
+
+// Dense grid for drivers without tessellation: a fixed square of vertices
+// around the camera, each displaced here rather than in a tessellation
+// evaluation shader.
+
+uniform mat4 view_projection;
+uniform vec2 camera_offset;
+
+const int N = 512;
+const float SCALE = 2.0;
+
+layout (location = 0) out vec2 uv_out;
+layout (location = 1) out vec3 position_out;
+
+void main() {
+  int line_count = N + 1;
+  int x = gl_VertexID % line_count;
+  int y = gl_VertexID / line_count;
+
+  // Follows the camera in whole cells, like ocean.vert
+  vec2 origin = floor(camera_offset / SCALE) * SCALE;
+  vec2 position_2d = (vec2(x, y) - float(N / 2)) * SCALE + origin;
+
+  uv_out = ocean_uv(position_2d);
+
+  vec3 position = vec3(position_2d.x, 0.0, position_2d.y);
+  position += ocean_displacement(uv_out);
+
+  gl_Position = view_projection * vec4(position, 1.0);
+  position_out = position;
+}
@@ -0,0 +1,47 @@
+#pragma include "ocean_displacement.glsl"
+
+// Projected grid: a regular grid over the screen, projected onto the mean
+// water level. Its density follows the perspective, and it reaches the
+// horizon wherever the camera looks.
+
+uniform mat4 view_projection;
+uniform mat4 inverse_view_projection;
+uniform vec3 camera_pos;
+
+uniform ivec2 grid_size; // vertices along each screen axis
+uniform float overscan; // fraction of the screen added on every side
+uniform float max_distance;
+uniform float displacement_fade; // distance at which waves are flattened out
+
+layout (location = 0) out vec2 uv_out;
+layout (location = 1) out vec3 position_out;
+
+void main() {
+  ivec2 cell = ivec2(gl_VertexID % grid_size.x, gl_VertexID / grid_size.x);
+  vec2 ndc = vec2(cell) / vec2(grid_size - 1) * 2.0 - 1.0;
+
+  // The grid extends past the screen, so that waves displaced sideways near
+  // its edges do not pull the surface away from them
+  ndc *= 1.0 + overscan;
+
+  vec4 point = inverse_view_projection * vec4(ndc, 0.0, 1.0);
+  vec3 ray = normalize(point.xyz / point.w - camera_pos);
+
+  // Rays at or above the horizon are bent down to meet the water far away,
+  // which folds the part of the grid above the horizon onto it
+  float height = max(camera_pos.y, 0.01);
+  float descent = max(-ray.y, height / max_distance);
+  float distance = height / descent;
+  vec3 position = camera_pos + ray * distance;
+  position.y = 0.0;
+
+  uv_out = ocean_uv(position.xz);
+
+  // Far away the grid is too coarse to follow the waves, and sampling them
+  // would only alias
+  float fade = clamp(1.0 - distance / displacement_fade, 0.0, 1.0);
+  position += ocean_displacement(uv_out) * fade;
+
+  gl_Position = view_projection * vec4(position, 1.0);
+  position_out = position;
+}
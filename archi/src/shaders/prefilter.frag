@@ -0,0 +1,69 @@
+#pragma include "equirectangular.glsl"
+#pragma include "cubemap.glsl"
+#pragma include "brdf.glsl"
+
+in vec2 layer_uv;
+flat in int layer;
+
+out vec4 frag;
+
+uniform sampler2D radiance;
+uniform float roughness;
+
+const int SAMPLES = 128;
+
+vec2 hammersley(int i) {
+  uint bits = uint(i);
+  bits = (bits << 16u) | (bits >> 16u);
+  bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+  bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+  bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+  bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+  return vec2(float(i) / float(SAMPLES), float(bits) * 2.3283064365386963e-10);
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 n, float alpha) {
+  float phi = 2.0 * PI * xi.x;
+  float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y));
+  float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+  vec3 h = vec3(sin_theta * cos(phi), sin_theta * sin(phi), cos_theta);
+
+  vec3 up = abs(n.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+  vec3 tangent = normalize(cross(up, n));
+  vec3 bitangent = cross(n, tangent);
+  return tangent * h.x + bitangent * h.y + n * h.z;
+}
+
+// The sky convolved with a GGX lobe, assuming the view and reflection
+// directions coincide with the normal, into each face of a cubemap. Alpha is
+// roughness squared.
+void main() {
+  float alpha = roughness * roughness;
+
+  vec3 n = normalize(cube_face_direction(layer, layer_uv * 2.0 - 1.0));
+
+  vec2 size = textureSize(radiance, 0);
+  float texel_solid_angle = 4.0 * PI / (size.x * size.y);
+
+  vec3 sum = vec3(0.0);
+  float weight = 0.0;
+  for (int i = 0; i < SAMPLES; i++) {
+    vec3 h = importance_sample_ggx(hammersley(i), n, alpha);
+    vec3 l = 2.0 * dot(n, h) * h - n;
+    float n_dot_l = dot(n, l);
+    if (n_dot_l <= 0.0) continue;
+
+    // Sample a mip level matching the footprint of the sample to keep
+    // the filtered result free of fireflies
+    float n_dot_h = max(dot(n, h), 0.0);
+    float pdf = d_ggx(n_dot_h, alpha) / 4.0;
+    float sample_solid_angle = 1.0 / (float(SAMPLES) * pdf + 0.0001);
+    float lod = 0.5 * log2(sample_solid_angle / texel_solid_angle) + 1.0;
+
+    vec3 color = textureLod(radiance, equirectangular(l), max(lod, 0.0)).rgb;
+    sum += color * n_dot_l;
+    weight += n_dot_l;
+  }
+
+  frag = vec4(sum / max(weight, 0.0001), 1.0);
+}
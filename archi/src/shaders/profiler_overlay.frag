@@ -0,0 +1,38 @@
+in vec2 uv;
+
+out vec4 frag;
+
+// One texel per scope, with its average CPU and GPU milliseconds
+uniform sampler2D scope_times;
+uniform int rows;
+// Milliseconds across the whole width
+uniform float span;
+
+vec3 row_color(int row) {
+  float hue = fract(float(row) * 0.618034);
+  vec3 rgb = clamp(abs(fract(hue + vec3(0.0, 2.0, 1.0) / 3.0) * 6.0 - 3.0) - 1.0, 0.0, 1.0);
+  return mix(vec3(1.0), rgb, 0.7);
+}
+
+void main() {
+  float y = (1.0 - uv.y) * float(rows);
+  int row = int(y);
+  float within = fract(y);
+  float ms = uv.x * span;
+
+  vec2 times = texelFetch(scope_times, ivec2(row, 0), 0).xy;
+
+  frag = vec4(0.0, 0.0, 0.0, 0.6);
+
+  // A tick every millisecond
+  if (fract(ms) < span / 400.0) {
+    frag = vec4(vec3(0.3), 0.8);
+  }
+
+  // GPU time on top, CPU time below and dimmer
+  if (within > 0.15 && within < 0.5 && ms < times.y) {
+    frag = vec4(row_color(row), 1.0);
+  } else if (within > 0.5 && within < 0.85 && ms < times.x) {
+    frag = vec4(row_color(row) * 0.5, 1.0);
+  }
+}
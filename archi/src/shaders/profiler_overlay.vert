@@ -0,0 +1,9 @@
+out vec2 uv;
+
+// Corners of the overlay in normalized device coordinates, min then max
+uniform vec4 rect;
+
+void main() {
+  uv = vec2(gl_VertexID % 2, gl_VertexID / 2);
+  gl_Position = vec4(mix(rect.xy, rect.zw, uv), 0.0, 1.0);
+}
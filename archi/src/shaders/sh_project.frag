@@ -0,0 +1,33 @@
+#pragma include "equirectangular.glsl"
+#pragma include "sh.glsl"
+
+out vec4 frag;
+
+uniform sampler2D radiance;
+uniform int sample_lod;
+
+// Every pixel of the 9x1 target integrates one SH coefficient over the whole
+// sky, mirroring skybox/sh.rs
+void main() {
+  int index = int(gl_FragCoord.x);
+
+  ivec2 size = textureSize(radiance, sample_lod);
+  float texel_area = (2.0 * PI / float(size.x)) * (PI / float(size.y));
+
+  vec3 sum = vec3(0.0);
+  for (int y = 0; y < size.y; y++) {
+    for (int x = 0; x < size.x; x++) {
+      vec2 uv = (vec2(x, y) + 0.5) / vec2(size);
+      vec3 d = equirectangular_direction(uv);
+      float solid_angle = texel_area * sqrt(1.0 - d.y * d.y);
+
+      float basis[9];
+      sh_basis(d, basis);
+
+      vec3 color = texelFetch(radiance, ivec2(x, y), sample_lod).rgb;
+      sum += color * solid_angle * basis[index];
+    }
+  }
+
+  frag = vec4(sum, 1.0);
+}
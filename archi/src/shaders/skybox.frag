@@ -1,20 +1,10 @@
-#pragma include "tonemap.glsl"
-#pragma include "equirectangular.glsl"
-
-uniform float exposure;
-uniform sampler2D sky_texture;
+uniform samplerCube sky_texture;
 
 in vec3 sample_direction;
 
 out vec4 frag;
 
 void main() {
-  frag = vec4(1.0);
-  vec3 direction = normalize(sample_direction);
-
-  vec2 uv = equirectangular(direction);
-  vec3 atmosphere_color = texture(sky_texture, uv).rgb;
-
-  frag.rgb = tonemap(atmosphere_color, exposure);
+  frag.rgb = texture(sky_texture, sample_direction).rgb;
   frag.a = 1.0;
 }
@@ -0,0 +1,14 @@
+#pragma include "complex.glsl"
+
+uniform sampler2D spectrum_texture;
+uniform sampler2D kernel_texture;
+
+out vec4 frag;
+
+// Both complex signals of the spectrum times those of the kernel
+void main() {
+  ivec2 xy = ivec2(gl_FragCoord.xy);
+  vec4 s = texelFetch(spectrum_texture, xy, 0);
+  vec4 k = texelFetch(kernel_texture, xy, 0);
+  frag = vec4(cmul(s.xy, k.xy), cmul(s.zw, k.zw));
+}
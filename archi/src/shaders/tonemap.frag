@@ -0,0 +1,22 @@
+#pragma include "tonemap.glsl"
+
+in vec2 uv;
+
+out vec4 frag;
+
+uniform sampler2D hdr_texture;
+uniform sampler2D exposure_texture;
+uniform int auto_exposure;
+uniform float exposure;
+uniform int tonemap_operator;
+
+void main() {
+  vec3 hdr = texture(hdr_texture, uv).rgb;
+
+  float scale = exposure;
+  if (auto_exposure != 0) {
+    scale *= texelFetch(exposure_texture, ivec2(0), 0).r;
+  }
+
+  frag = vec4(tonemap(hdr * scale, tonemap_operator), 1.0);
+}
@@ -1,27 +1,225 @@
 use crate::context::Context;
+use crate::debug::{
+    capture::label_texture, inspector::Inspectable, DebugGroup,
+};
+use anyhow::Context as _;
 use luminance_derive::{Semantics, UniformInterface, Vertex};
 use luminance_front::{
+    context::GraphicsContext,
     depth_test::DepthComparison,
+    framebuffer::Framebuffer,
     pipeline::{Pipeline, TextureBinding},
-    pixel::{Floating, RGB32F},
+    pixel::{Floating, RGB32F, RGBA32F},
     render_state::RenderState,
     shader::{Program, Uniform},
     shading_gate::ShadingGate,
     tess::{Mode, Tess, TessBuilder},
-    texture::{Dim2, Sampler, Texture, Wrap},
+    texture::{
+        CubeFace, Cubemap, Dim2, GenMipmaps, MagFilter, MinFilter, Sampler,
+        Texture, Wrap,
+    },
 };
 
+mod cube;
+mod sh;
+
+const QUAD_VS_SRC: crate::shader::ShaderSource =
+    crate::shader_source!("./shaders/quad.vert");
+
+/// Cubemaps converted from an equirectangular sky get a quarter of its width
+/// per face, up to this size.
+const MAX_FACE_SIZE: u32 = 2048;
+
+/// Width of the downsampled copy of the sky that image based lighting is
+/// computed from.
+const RADIANCE_WIDTH: usize = 1024;
+
+/// Number of prefiltered specular levels, not counting the unfiltered sky.
+/// ocean.frag has a sampler for each.
+pub const SPECULAR_LEVELS: usize = 5;
+const SPECULAR_FACE_SIZE: u32 = 128;
+const SPECULAR_LABELS: [&str; SPECULAR_LEVELS] = [
+    "sky specular 1",
+    "sky specular 2",
+    "sky specular 3",
+    "sky specular 4",
+    "sky specular 5",
+];
+
+/// Mip level of the radiance texture the GPU SH projection integrates over.
+const SH_SAMPLE_LOD: i32 = 2;
+
+pub type SkyTexture = Texture<Cubemap, RGBA32F>;
+pub type SpecularTexture = Texture<Cubemap, RGBA32F>;
+pub type IrradianceTexture = Texture<Dim2, RGBA32F>;
+
+#[derive(UniformInterface)]
+struct CubemapInterface {
+    equirectangular_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    layers: Uniform<i32>,
+}
+
+#[derive(UniformInterface)]
+struct PrefilterInterface {
+    radiance: Uniform<TextureBinding<Dim2, Floating>>,
+    layers: Uniform<i32>,
+    roughness: Uniform<f32>,
+}
+
+#[derive(UniformInterface)]
+struct ShProjectInterface {
+    radiance: Uniform<TextureBinding<Dim2, Floating>>,
+    sample_lod: Uniform<i32>,
+}
+
 #[derive(UniformInterface)]
 pub struct SkyboxShaderInterface {
-    sky_texture: Uniform<TextureBinding<Dim2, Floating>>,
+    sky_texture: Uniform<TextureBinding<Cubemap, Floating>>,
     view_projection: Uniform<[[f32; 4]; 4]>,
-    exposure: Uniform<f32>,
 }
 
 type SkyboxShader = Program<(), (), SkyboxShaderInterface>;
 
+/// A directional light standing in for the sun, which is too small and too
+/// bright to be reflected properly by sampling the sky texture alone.
+#[derive(Debug, Clone, Copy)]
+pub struct Sun {
+    pub direction: glm::Vec3,
+    /// Irradiance arriving from the sun, in the same units as the sky texture.
+    pub color: glm::Vec3,
+    /// Angular radius of the sun disc, in radians.
+    pub angular_radius: f32,
+}
+
+impl Default for Sun {
+    fn default() -> Self {
+        Self {
+            direction: glm::normalize(&glm::vec3(-1.0, 0.3, -0.6)),
+            color: glm::vec3(40.0, 38.0, 34.0),
+            angular_radius: 0.00465,
+        }
+    }
+}
+
+/// Height fog between the camera and the ocean, lit by the sky near the
+/// horizon and by the sun.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    /// Extinction per meter at the base height, per colour channel. Blue
+    /// being absorbed faster than red leaves mostly red light to reach the
+    /// eye from far away. Zero turns the fog off.
+    pub extinction: glm::Vec3,
+    /// How quickly the fog thins out with height, per meter.
+    pub height_falloff: f32,
+    pub base_height: f32,
+    /// Strength of the glow around the sun from light scattered by the fog.
+    pub sun_scattering: f32,
+    /// Henyey-Greenstein anisotropy of that glow, towards 1 for a tighter
+    /// glow.
+    pub anisotropy: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            extinction: glm::vec3(1.5e-4, 2.0e-4, 3.0e-4),
+            height_falloff: 0.01,
+            base_height: 0.0,
+            sun_scattering: 0.05,
+            anisotropy: 0.7,
+        }
+    }
+}
+
+const FOG_FIELDS: [&str; 5] = [
+    "extinction",
+    "height_falloff",
+    "base_height",
+    "sun_scattering",
+    "anisotropy",
+];
+
+impl Fog {
+    /// Parse a fog description with one field per line, as its name followed
+    /// by its value, e.g. `extinction 1.5e-4 2.0e-4 3.0e-4`. Fields left out
+    /// keep their default value.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut fog = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let name = match words.next() {
+                Some(name) => name,
+                None => continue,
+            };
+            let values = words
+                .map(str::parse)
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|e| anyhow::anyhow!("line {}: {}", number + 1, e))?;
+
+            match (name, &values[..]) {
+                ("extinction", &[r, g, b]) => {
+                    fog.extinction = glm::vec3(r, g, b)
+                }
+                ("height_falloff", &[value]) => fog.height_falloff = value,
+                ("base_height", &[value]) => fog.base_height = value,
+                ("sun_scattering", &[value]) => fog.sun_scattering = value,
+                ("anisotropy", &[value]) => fog.anisotropy = value,
+                _ if FOG_FIELDS.contains(&name) => anyhow::bail!(
+                    "line {}: wrong number of values for {}",
+                    number + 1,
+                    name
+                ),
+                _ => anyhow::bail!(
+                    "line {}: unknown fog field {:?}",
+                    number + 1,
+                    name
+                ),
+            }
+        }
+
+        Ok(fog)
+    }
+}
+
+/// Where the sky is loaded from. Both variants hold Radiance HDR images.
+pub enum SkySource<'a> {
+    /// A single equirectangular panorama, converted to a cubemap on the GPU.
+    Equirectangular(&'a [u8]),
+    /// Six square faces in the order +x, -x, +y, -y, +z, -z, with the first
+    /// row of the side faces pointing up.
+    Faces([&'a [u8]; 6]),
+}
+
+/// File names of the faces looked for in `$ARCHI_SKYBOX_FACES`.
+const FACE_FILES: [&str; 6] =
+    ["px.hdr", "nx.hdr", "py.hdr", "ny.hdr", "pz.hdr", "nz.hdr"];
+
+/// Optional file next to the faces describing the fog of the scene, in the
+/// format read by `Fog::parse`.
+const FOG_FILE: &str = "fog.txt";
+
+/// The sky used when `$ARCHI_SKYBOX_FACES` is not set.
+const BUILTIN_PANORAMA: &[u8] =
+    include_bytes!("../assets/colorful_studio_8k.hdr");
+
+fn read_fog(path: &std::path::Path) -> anyhow::Result<Fog> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    Fog::parse(&text)
+        .with_context(|| format!("failed to parse {}", path.display()))
+}
+
 pub struct Skybox {
-    pub sky_texture: Texture<Dim2, RGB32F>,
+    pub sky_texture: SkyTexture,
+    /// The sky convolved with GGX lobes of increasing roughness. Luminance
+    /// cannot render into individual mip levels, so the chain is stored as a
+    /// cubemap per level instead, level `l` holding roughness
+    /// `(l + 1) / SPECULAR_LEVELS`. Roughness zero is the sky texture itself.
+    pub specular: Vec<SpecularTexture>,
+    /// SH9 coefficients of the sky radiance, in a 9x1 texture.
+    pub irradiance_sh: IrradianceTexture,
+    pub sun: Sun,
+    pub fog: Fog,
     tess: Tess<CubeVertex, u32>,
     shader: SkyboxShader,
 }
@@ -50,8 +248,24 @@ pub struct ImageData {
     pub data: Vec<(f32, f32, f32)>,
 }
 
-fn load_sky(context: &mut Context) -> anyhow::Result<Texture<Dim2, RGB32F>> {
-    let file: &[u8] = include_bytes!("../assets/colorful_studio_8k.hdr");
+fn read_hdr(file: &[u8]) -> anyhow::Result<ImageData> {
+    let image = radiant::load(file)?;
+    let data = image.data.iter().map(|t| (t.r, t.g, t.b)).collect();
+
+    Ok(ImageData {
+        width: image.width,
+        height: image.height,
+        data,
+    })
+}
+
+/// Load an equirectangular sky, along with a box filtered copy no wider than
+/// `RADIANCE_WIDTH`. The sky is streamed into the texture one scanline at a
+/// time, since it can be too large to comfortably keep around on the CPU.
+fn load_equirectangular(
+    context: &mut Context,
+    file: &[u8],
+) -> anyhow::Result<(Texture<Dim2, RGB32F>, ImageData)> {
     let mut loader = radiant::Loader::new(file)?.scanlines();
 
     let width = loader.width as u32;
@@ -59,13 +273,25 @@ fn load_sky(context: &mut Context) -> anyhow::Result<Texture<Dim2, RGB32F>> {
 
     let mut buf = vec![radiant::Rgb::zero(); loader.width];
 
+    let factor = (loader.width / RADIANCE_WIDTH).max(1);
+    let (radiance_width, radiance_height) =
+        (loader.width / factor, loader.height / factor);
+    let mut radiance = ImageData {
+        width: radiance_width,
+        height: radiance_height,
+        data: vec![(0.0, 0.0, 0.0); radiance_width * radiance_height],
+    };
+    let weight = 1.0 / (factor * factor) as f32;
+
     let mut texture = Texture::new(
         context,
         [width, height],
         0,
         Sampler {
-            wrap_r: Wrap::Repeat,
-            wrap_s: Wrap::ClampToEdge,
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::ClampToEdge,
+            mag_filter: MagFilter::Linear,
+            min_filter: MinFilter::Linear,
             ..Default::default()
         },
     )?;
@@ -73,18 +299,364 @@ fn load_sky(context: &mut Context) -> anyhow::Result<Texture<Dim2, RGB32F>> {
     for y in 0..height {
         loader.read_scanline(&mut buf)?;
         texture.upload_part_raw(
-            luminance::texture::GenMipmaps::No,
+            GenMipmaps::No,
             [0, y],
             [width, 1],
             bytemuck::cast_slice(&buf),
         )?;
+
+        let row = y as usize / factor;
+        if row < radiance.height {
+            let start = row * radiance.width;
+            let row = &mut radiance.data[start..start + radiance.width];
+            for (x, texel) in buf.iter().enumerate() {
+                if let Some((r, g, b)) = row.get_mut(x / factor) {
+                    *r += texel.r * weight;
+                    *g += texel.g * weight;
+                    *b += texel.b * weight;
+                }
+            }
+        }
+    }
+
+    Ok((texture, radiance))
+}
+
+fn sky_sampler() -> Sampler {
+    Sampler {
+        mag_filter: MagFilter::Linear,
+        min_filter: MinFilter::Linear,
+        ..Default::default()
+    }
+}
+
+fn equirectangular_to_cubemap(
+    context: &mut Context,
+    equirectangular: &mut Texture<Dim2, RGB32F>,
+) -> anyhow::Result<SkyTexture> {
+    let [width, _] = equirectangular.size();
+    let face_size = u32::min(width / 4, MAX_FACE_SIZE);
+
+    let framebuffer: Framebuffer<Cubemap, RGBA32F, ()> =
+        context.new_framebuffer(face_size, 0, sky_sampler())?;
+
+    let mut shader: Program<(), (), CubemapInterface> =
+        crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            Some(crate::shader_source!("./shaders/layered.geom")),
+            crate::shader_source!("./shaders/cubemap.frag"),
+        )?;
+
+    let tess = quad_tess(context)?;
+
+    let _group = DebugGroup::new("equirectangular to cubemap");
+    context
+        .new_pipeline_gate()
+        .pipeline(
+            &framebuffer,
+            &Default::default(),
+            |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                let equirectangular = pipeline.bind_texture(equirectangular)?;
+                shader_gate.shade(
+                    &mut shader,
+                    |mut iface, uni, mut render_gate| {
+                        iface.set(
+                            &uni.equirectangular_texture,
+                            equirectangular.binding(),
+                        );
+                        iface.set(&uni.layers, 6);
+                        render_gate
+                            .render(&Default::default(), |mut tess_gate| {
+                                tess_gate.render(&tess)
+                            })
+                    },
+                )
+            },
+        )
+        .into_result()?;
+
+    Ok(framebuffer.into_color_slot())
+}
+
+/// Load the six faces of a cubemap sky, along with an equirectangular copy
+/// no wider than `RADIANCE_WIDTH` resampled from them.
+fn load_faces(
+    context: &mut Context,
+    files: [&[u8]; 6],
+) -> anyhow::Result<(SkyTexture, ImageData)> {
+    let faces = files
+        .iter()
+        .map(|file| read_hdr(file))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let size = faces[0].width;
+    for (face, name) in faces.iter().zip(FACE_FILES.iter()) {
+        if face.width != size || face.height != size {
+            anyhow::bail!(
+                "cubemap face {} is {}x{}, expected {}x{}",
+                name,
+                face.width,
+                face.height,
+                size,
+                size,
+            );
+        }
     }
 
+    let mut texture = Texture::new(context, size as u32, 0, sky_sampler())?;
+
+    let cube_faces = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+    for (face, &cube_face) in faces.iter().zip(cube_faces.iter()) {
+        let texels: Vec<_> =
+            face.data.iter().map(|&(r, g, b)| (r, g, b, 1.0)).collect();
+        texture.upload_part(
+            GenMipmaps::No,
+            ([0, 0], cube_face),
+            size as u32,
+            &texels,
+        )?;
+    }
+
+    let width = usize::min(RADIANCE_WIDTH, size * 4);
+    let height = width / 2;
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let uv = glm::vec2(
+                (x as f32 + 0.5) / width as f32,
+                (y as f32 + 0.5) / height as f32,
+            );
+            let (face, s, t) = cube::face_coordinates(&sh::direction(uv));
+            let texel = |c: f32| {
+                let i = ((c + 1.0) / 2.0 * size as f32) as usize;
+                i.min(size - 1)
+            };
+            data.push(faces[face].data[texel(t) * size + texel(s)]);
+        }
+    }
+
+    let radiance = ImageData {
+        width,
+        height,
+        data,
+    };
+
+    Ok((texture, radiance))
+}
+
+fn radiance_texture(
+    context: &mut Context,
+    image: &ImageData,
+) -> anyhow::Result<Texture<Dim2, RGB32F>> {
+    let size = [image.width as u32, image.height as u32];
+    let mipmaps = (31 - u32::leading_zeros(size[0])) as usize;
+
+    let mut texture = Texture::new(
+        context,
+        size,
+        mipmaps,
+        Sampler {
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::ClampToEdge,
+            mag_filter: MagFilter::Linear,
+            min_filter: MinFilter::LinearMipmapLinear,
+            ..Default::default()
+        },
+    )?;
+
+    texture.upload(GenMipmaps::Yes, &image.data)?;
+
     Ok(texture)
 }
 
+fn quad_tess(context: &mut Context) -> anyhow::Result<Tess<()>> {
+    let tess = context
+        .new_tess()
+        .set_mode(Mode::TriangleStrip)
+        .set_vertex_nb(4)
+        .build()?;
+
+    Ok(tess)
+}
+
+fn prefilter_specular(
+    context: &mut Context,
+    radiance: &mut Texture<Dim2, RGB32F>,
+) -> anyhow::Result<Vec<SpecularTexture>> {
+    let mut shader: Program<(), (), PrefilterInterface> =
+        crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            Some(crate::shader_source!("./shaders/layered.geom")),
+            crate::shader_source!("./shaders/prefilter.frag"),
+        )?;
+
+    let tess = quad_tess(context)?;
+
+    let _group = DebugGroup::new("prefilter specular");
+    let mut levels = Vec::with_capacity(SPECULAR_LEVELS);
+    for level in 0..SPECULAR_LEVELS {
+        let framebuffer: Framebuffer<Cubemap, RGBA32F, ()> =
+            context.new_framebuffer(SPECULAR_FACE_SIZE, 0, sky_sampler())?;
+        let roughness = (level + 1) as f32 / SPECULAR_LEVELS as f32;
+
+        context
+            .new_pipeline_gate()
+            .pipeline(
+                &framebuffer,
+                &Default::default(),
+                |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                    let radiance = pipeline.bind_texture(&mut *radiance)?;
+                    shader_gate.shade(
+                        &mut shader,
+                        |mut iface, uni, mut render_gate| {
+                            iface.set(&uni.radiance, radiance.binding());
+                            iface.set(&uni.layers, 6);
+                            iface.set(&uni.roughness, roughness);
+                            render_gate
+                                .render(&Default::default(), |mut tess_gate| {
+                                    tess_gate.render(&tess)
+                                })
+                        },
+                    )
+                },
+            )
+            .into_result()?;
+
+        levels.push(framebuffer.into_color_slot());
+    }
+
+    Ok(levels)
+}
+
+fn project_irradiance(
+    context: &mut Context,
+    radiance: &mut Texture<Dim2, RGB32F>,
+) -> anyhow::Result<IrradianceTexture> {
+    let sampler = Sampler {
+        mag_filter: MagFilter::Nearest,
+        min_filter: MinFilter::Nearest,
+        ..Default::default()
+    };
+
+    let framebuffer: Framebuffer<Dim2, RGBA32F, ()> =
+        context.new_framebuffer([9, 1], 0, sampler)?;
+
+    let mut shader: Program<(), (), ShProjectInterface> =
+        crate::shader::from_sources(
+            context,
+            None,
+            QUAD_VS_SRC,
+            None,
+            crate::shader_source!("./shaders/sh_project.frag"),
+        )?;
+
+    let tess = quad_tess(context)?;
+
+    let _group = DebugGroup::new("project irradiance");
+    context
+        .new_pipeline_gate()
+        .pipeline(
+            &framebuffer,
+            &Default::default(),
+            |pipeline, mut shader_gate| -> anyhow::Result<()> {
+                let radiance = pipeline.bind_texture(radiance)?;
+                shader_gate.shade(
+                    &mut shader,
+                    |mut iface, uni, mut render_gate| {
+                        iface.set(&uni.radiance, radiance.binding());
+                        iface.set(&uni.sample_lod, SH_SAMPLE_LOD);
+                        render_gate
+                            .render(&Default::default(), |mut tess_gate| {
+                                tess_gate.render(&tess)
+                            })
+                    },
+                )
+            },
+        )
+        .into_result()?;
+
+    Ok(framebuffer.into_color_slot())
+}
+
 impl Skybox {
+    /// Load the sky from the faces in `$ARCHI_SKYBOX_FACES` if it is set,
+    /// otherwise the built in panorama. The fog is read from the file named
+    /// by `$ARCHI_FOG` if it is set, whichever the sky, then from the
+    /// `fog.txt` next to the faces if there is one, and is the default fog
+    /// otherwise.
     pub fn new(context: &mut Context) -> anyhow::Result<Self> {
+        let fog = match std::env::var_os("ARCHI_FOG") {
+            Some(path) => Some(read_fog(std::path::Path::new(&path))?),
+            None => None,
+        };
+
+        match std::env::var_os("ARCHI_SKYBOX_FACES") {
+            Some(dir) => {
+                let dir = std::path::Path::new(&dir);
+                let faces = FACE_FILES
+                    .iter()
+                    .map(|name| {
+                        let path = dir.join(name);
+                        std::fs::read(&path).with_context(|| {
+                            format!("failed to read {}", path.display())
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let face = |i: usize| faces[i].as_slice();
+
+                let fog_path = dir.join(FOG_FILE);
+                let fog = match fog {
+                    Some(fog) => fog,
+                    None if fog_path.exists() => read_fog(&fog_path)?,
+                    None => Fog::default(),
+                };
+
+                Self::with_source(
+                    context,
+                    SkySource::Faces([
+                        face(0),
+                        face(1),
+                        face(2),
+                        face(3),
+                        face(4),
+                        face(5),
+                    ]),
+                    fog,
+                )
+            }
+            None => Self::with_source(
+                context,
+                SkySource::Equirectangular(BUILTIN_PANORAMA),
+                fog.unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// The built in panorama, with the default fog.
+    pub fn builtin(context: &mut Context) -> anyhow::Result<Self> {
+        Self::with_source(
+            context,
+            SkySource::Equirectangular(BUILTIN_PANORAMA),
+            Fog::default(),
+        )
+    }
+
+    pub fn with_source(
+        context: &mut Context,
+        source: SkySource,
+        fog: Fog,
+    ) -> anyhow::Result<Self> {
         let tess = {
             let (vertices, indices) = {
                 let n_vertices = 24;
@@ -143,27 +715,61 @@ impl Skybox {
             crate::shader_source!("./shaders/skybox.frag"),
         )?;
 
-        let sky_texture = load_sky(context)?;
+        let (mut sky_texture, radiance_image) = match source {
+            SkySource::Equirectangular(file) => {
+                let (mut equirectangular, radiance) =
+                    load_equirectangular(context, file)?;
+                let cubemap =
+                    equirectangular_to_cubemap(context, &mut equirectangular)?;
+                (cubemap, radiance)
+            }
+            SkySource::Faces(files) => load_faces(context, files)?,
+        };
+
+        let mut radiance = radiance_texture(context, &radiance_image)?;
+        let mut specular = prefilter_specular(context, &mut radiance)?;
+        let mut irradiance_sh = project_irradiance(context, &mut radiance)?;
+
+        label_texture(context, &mut sky_texture, "sky")?;
+        label_texture(context, &mut radiance, "sky radiance")?;
+        for (level, label) in specular.iter_mut().zip(&SPECULAR_LABELS) {
+            label_texture(context, level, label)?;
+        }
+        label_texture(context, &mut irradiance_sh, "sky irradiance")?;
 
         Ok(Self {
             sky_texture,
+            specular,
+            irradiance_sh,
+            sun: Default::default(),
+            fog,
             tess,
             shader,
         })
     }
 
+    pub fn inspectables(&mut self) -> Vec<Inspectable> {
+        let mut textures: Vec<Inspectable> =
+            vec![("sky", &mut self.sky_texture)];
+        for (level, label) in self.specular.iter_mut().zip(&SPECULAR_LABELS) {
+            textures.push((*label, level));
+        }
+        textures.push(("sky irradiance", &mut self.irradiance_sh));
+        textures
+    }
+
     pub fn render(
         &mut self,
         pipeline: &mut Pipeline,
         shader_gate: &mut ShadingGate,
         view: glm::Mat4,
         projection: glm::Mat4,
-        exposure: f32,
     ) -> anyhow::Result<()> {
         let Self {
             shader,
             tess,
             sky_texture,
+            ..
         } = self;
 
         let mut view = view;
@@ -178,7 +784,6 @@ impl Skybox {
         shader_gate.shade(shader, |mut iface, uni, mut render_gate| {
             iface.set(&uni.sky_texture, sky_texture.binding());
             iface.set(&uni.view_projection, view_projection.into());
-            iface.set(&uni.exposure, exposure);
 
             let state = RenderState::default()
                 .set_depth_test(DepthComparison::LessOrEqual);
@@ -186,3 +791,29 @@ impl Skybox {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fog_fields_left_out_keep_defaults() {
+        let fog = Fog::parse("extinction 1e-3 2e-3 4e-3\n\n  anisotropy 0.5\n")
+            .unwrap();
+        assert_eq!(
+            fog,
+            Fog {
+                extinction: glm::vec3(1e-3, 2e-3, 4e-3),
+                anisotropy: 0.5,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn fog_rejects_bad_lines() {
+        assert!(Fog::parse("extinction 1e-3").is_err());
+        assert!(Fog::parse("density 0.1").is_err());
+        assert!(Fog::parse("anisotropy high").is_err());
+    }
+}
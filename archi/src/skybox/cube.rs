@@ -0,0 +1,91 @@
+//! Cubemap face addressing, matching the OpenGL convention and `cubemap.glsl`.
+//! Faces are numbered +x, -x, +y, -y, +z, -z, and face coordinates `s` and
+//! `t` run from -1 to 1, with `t = -1` being the first row of the face.
+
+/// The face a direction points at, and where on that face it lands.
+pub fn face_coordinates(d: &glm::Vec3) -> (usize, f32, f32) {
+    let (x, y, z) = (d.x.abs(), d.y.abs(), d.z.abs());
+
+    let (face, s, t, major) = if x >= y && x >= z {
+        if d.x > 0.0 {
+            (0, -d.z, -d.y, x)
+        } else {
+            (1, d.z, -d.y, x)
+        }
+    } else if y >= z {
+        if d.y > 0.0 {
+            (2, d.x, d.z, y)
+        } else {
+            (3, d.x, -d.z, y)
+        }
+    } else if d.z > 0.0 {
+        (4, d.x, -d.y, z)
+    } else {
+        (5, -d.x, -d.y, z)
+    };
+
+    (face, s / major, t / major)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Direction through the point `(s, t)` on `face`, as `cube_face_direction`
+    /// in cubemap.glsl.
+    fn direction(face: usize, s: f32, t: f32) -> glm::Vec3 {
+        match face {
+            0 => glm::vec3(1.0, -t, -s),
+            1 => glm::vec3(-1.0, -t, s),
+            2 => glm::vec3(s, 1.0, t),
+            3 => glm::vec3(s, -1.0, -t),
+            4 => glm::vec3(s, -t, 1.0),
+            _ => glm::vec3(-s, -t, -1.0),
+        }
+    }
+
+    #[test]
+    fn face_centers() {
+        let axes = [
+            glm::Vec3::x(),
+            -glm::Vec3::x(),
+            glm::Vec3::y(),
+            -glm::Vec3::y(),
+            glm::Vec3::z(),
+            -glm::Vec3::z(),
+        ];
+
+        for (face, axis) in axes.iter().enumerate() {
+            assert_eq!(direction(face, 0.0, 0.0), *axis);
+            assert_eq!(face_coordinates(axis), (face, 0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn first_row_of_side_faces_is_up() {
+        // Faces loaded by `load_faces` are stored with their first row up, so
+        // looking up from the middle of a side face moves towards t = -1
+        let sides = [
+            glm::Vec3::x(),
+            -glm::Vec3::x(),
+            glm::Vec3::z(),
+            -glm::Vec3::z(),
+        ];
+        for (&face, axis) in [0, 1, 4, 5].iter().zip(&sides) {
+            let up = axis + glm::vec3(0.0, 0.5, 0.0);
+            assert_eq!(face_coordinates(&up), (face, 0.0, -0.5));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for face in 0..6 {
+            for &(s, t) in &[(0.5, -0.25), (-0.9, 0.9), (0.1, 0.7)] {
+                let d = direction(face, s, t);
+                let (f, s2, t2) = face_coordinates(&d);
+                assert_eq!(f, face);
+                assert!((s - s2).abs() < 1e-6 && (t - t2).abs() < 1e-6);
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Spherical harmonics up to band 2 (SH9) of an equirectangular environment.
+//! The skybox projects onto them on the GPU, in sh_project.frag, which the
+//! tests mirror on the CPU.
+
+use std::f32::consts::PI;
+
+/// Direction of the texel center at `uv`, the inverse of the mapping in
+/// `equirectangular.glsl`.
+pub fn direction(uv: glm::Vec2) -> glm::Vec3 {
+    let azimuth = (uv.x - 0.5) * 2.0 * PI;
+    let elevation = (0.5 - uv.y) * PI;
+    glm::vec3(
+        elevation.cos() * azimuth.cos(),
+        elevation.sin(),
+        elevation.cos() * azimuth.sin(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::skybox::{ImageData, SH_SAMPLE_LOD};
+
+    type Coefficients = [glm::Vec3; 9];
+
+    /// Real spherical harmonics basis, in the same order as `sh.glsl`.
+    fn basis(d: &glm::Vec3) -> [f32; 9] {
+        let (x, y, z) = (d.x, d.y, d.z);
+        [
+            0.282_095,
+            0.488_603 * y,
+            0.488_603 * z,
+            0.488_603 * x,
+            1.092_548 * x * y,
+            1.092_548 * y * z,
+            0.315_392 * (3.0 * z * z - 1.0),
+            1.092_548 * x * z,
+            0.546_274 * (x * x - y * y),
+        ]
+    }
+
+    /// Project the radiance of an equirectangular image onto the SH9 basis.
+    fn project(image: &ImageData) -> Coefficients {
+        let mut coefficients = [glm::Vec3::zeros(); 9];
+
+        let width = image.width as f32;
+        let height = image.height as f32;
+        let texel_area = (2.0 * PI / width) * (PI / height);
+
+        for y in 0..image.height {
+            let v = (y as f32 + 0.5) / height;
+            for x in 0..image.width {
+                let u = (x as f32 + 0.5) / width;
+                let d = direction(glm::vec2(u, v));
+                // Texels shrink towards the poles
+                let solid_angle = texel_area * (1.0 - d.y * d.y).sqrt();
+
+                let (r, g, b) = image.data[y * image.width + x];
+                let radiance = glm::vec3(r, g, b) * solid_angle;
+
+                for (c, y) in coefficients.iter_mut().zip(basis(&d).iter()) {
+                    *c += radiance * *y;
+                }
+            }
+        }
+
+        coefficients
+    }
+
+    // Irradiance arriving at a surface facing `normal`, the same way
+    // `sh_irradiance` in sh.glsl evaluates it.
+    fn irradiance(
+        coefficients: &Coefficients,
+        normal: &glm::Vec3,
+    ) -> glm::Vec3 {
+        const BAND_FACTORS: [f32; 9] = [
+            PI,
+            2.0 * PI / 3.0,
+            2.0 * PI / 3.0,
+            2.0 * PI / 3.0,
+            PI / 4.0,
+            PI / 4.0,
+            PI / 4.0,
+            PI / 4.0,
+            PI / 4.0,
+        ];
+
+        let y = basis(normal);
+        let mut result = glm::Vec3::zeros();
+        for i in 0..9 {
+            result += coefficients[i] * BAND_FACTORS[i] * y[i];
+        }
+        result
+    }
+
+    fn environment(f: impl Fn(glm::Vec3) -> f32) -> ImageData {
+        let (width, height) = (256, 128);
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let u = (x as f32 + 0.5) / width as f32;
+                let v = (y as f32 + 0.5) / height as f32;
+                let value = f(direction(glm::vec2(u, v)));
+                data.push((value, value, value));
+            }
+        }
+
+        ImageData {
+            width,
+            height,
+            data,
+        }
+    }
+
+    fn assert_close(a: f32, b: f32, tolerance: f32) {
+        assert!((a - b).abs() <= tolerance, "{} is not close to {}", a, b);
+    }
+
+    #[test]
+    fn direction_matches_equirectangular() {
+        // equirectangular.glsl maps +y to the top row and +x to the center
+        let up = direction(glm::vec2(0.5, 0.0));
+        assert_close(up.y, 1.0, 1e-6);
+
+        let forward = direction(glm::vec2(0.5, 0.5));
+        assert_close(forward.x, 1.0, 1e-6);
+
+        let side = direction(glm::vec2(0.75, 0.5));
+        assert_close(side.z, 1.0, 1e-6);
+    }
+
+    #[test]
+    fn constant() {
+        let coefficients = project(&environment(|_| 1.0));
+
+        let dc = 4.0 * PI * 0.282_095;
+        assert_close(coefficients[0].x, dc, 1e-3);
+        for c in &coefficients[1..] {
+            assert_close(c.x, 0.0, 1e-3);
+        }
+
+        for normal in &[glm::Vec3::y(), -glm::Vec3::y(), glm::Vec3::x()] {
+            assert_close(irradiance(&coefficients, normal).x, PI, 1e-2);
+        }
+    }
+
+    #[test]
+    fn upper_hemisphere() {
+        let coefficients = project(&environment(|d| d.y.max(0.0)));
+
+        // Exact values are 2π/3 facing up and 0 facing down. Band 2 can only
+        // approximate the clamped cosine, so allow a few percent.
+        let up = irradiance(&coefficients, &glm::Vec3::y()).x;
+        let down = irradiance(&coefficients, &-glm::Vec3::y()).x;
+        assert_close(up, 2.0 * PI / 3.0, 0.05);
+        assert_close(down, 0.0, 0.05);
+    }
+
+    #[test]
+    fn colour_channels_are_independent() {
+        let mut image = environment(|_| 0.0);
+        for texel in image.data.iter_mut() {
+            *texel = (1.0, 2.0, 0.0);
+        }
+
+        let coefficients = project(&image);
+        assert_close(coefficients[0].y, 2.0 * coefficients[0].x, 1e-3);
+        assert_close(coefficients[0].z, 0.0, 1e-6);
+    }
+
+    // Every 2x2 texels averaged, `levels` times over, as mip levels are made
+    fn downsample(image: &ImageData, levels: i32) -> ImageData {
+        let mut image = ImageData {
+            width: image.width,
+            height: image.height,
+            data: image.data.clone(),
+        };
+        for _ in 0..levels {
+            let (width, height) = (image.width / 2, image.height / 2);
+            let at = |x: usize, y: usize| image.data[y * image.width + x];
+            let mut data = Vec::with_capacity(width * height);
+            for y in 0..height {
+                for x in 0..width {
+                    let texels = [
+                        at(2 * x, 2 * y),
+                        at(2 * x + 1, 2 * y),
+                        at(2 * x, 2 * y + 1),
+                        at(2 * x + 1, 2 * y + 1),
+                    ];
+                    let sum = texels.iter().fold((0.0, 0.0, 0.0), |s, t| {
+                        (s.0 + t.0, s.1 + t.1, s.2 + t.2)
+                    });
+                    data.push((sum.0 / 4.0, sum.1 / 4.0, sum.2 / 4.0));
+                }
+            }
+            image = ImageData {
+                width,
+                height,
+                data,
+            };
+        }
+        image
+    }
+
+    #[test]
+    fn sample_lod_loses_little() {
+        // The GPU integrates a smaller mip level, which should not matter
+        // for coefficients this smooth, even with a sharp sun in the sky
+        let sun = glm::normalize(&glm::vec3(-1.0, 0.3, -0.6));
+        let full = environment(|d| {
+            let glow = glm::dot(&d, &sun).max(0.0).powi(64) * 50.0;
+            1.0 + 2.0 * d.y.max(0.0) + glow
+        });
+        let coarse = downsample(&full, SH_SAMPLE_LOD);
+
+        let (full, coarse) = (project(&full), project(&coarse));
+        let scale = full[0].x;
+        for (a, b) in full.iter().zip(&coarse) {
+            assert_close(a.x / scale, b.x / scale, 5e-3);
+        }
+    }
+}